@@ -3,14 +3,20 @@
 import React, { useState, useEffect, useCallback } from 'react';
 import { useConnection, useWallet } from '@solana/wallet-adapter-react';
 import { WalletMultiButton } from '@solana/wallet-adapter-react-ui';
-import { LAMPORTS_PER_SOL } from '@solana/web3.js';
+import { LAMPORTS_PER_SOL, PublicKey } from '@solana/web3.js';
+import { AnchorProvider, BN, Idl, Program } from '@coral-xyz/anchor';
 import toast from 'react-hot-toast';
+import idl from '../target/idl/slot_machine.json';
 
 // Constants
 const MIN_BET_AMOUNT = 0.01; // SOL
 const MAX_BET_AMOUNT = 1.0; // SOL
 
-const SLOT_SYMBOLS = ['🍒', '🍋', '🍊', '🍇', '🔔', '💎', '7️⃣'];
+// Index = on-chain symbol id used in the paytable's reel strips and the SpinResult grid
+const SLOT_SYMBOLS = ['🍒', '🍋', '🍊', '🍇', '🔔', '💎', '7️⃣', '⭐'];
+
+// Machine whose settled spins can be shown; unset keeps the page a pure demo
+const GAME_CONFIG = process.env.NEXT_PUBLIC_GAME_CONFIG;
 
 export default function SlotMachinePage() {
   const { connection } = useConnection();
@@ -22,6 +28,8 @@ export default function SlotMachinePage() {
   const [isSpinning, setIsSpinning] = useState<boolean>(false);
   const [reels, setReels] = useState<string[]>(['🎰', '🎰', '🎰']);
   const [lastResult, setLastResult] = useState<string>('');
+  const [chainGrid, setChainGrid] = useState<string[][] | null>(null);
+  const [spinNonce, setSpinNonce] = useState<string>('');
   const [gameState, setGameState] = useState<'idle' | 'committed' | 'spinning' | 'revealing'>('idle');
   
   // Game State
@@ -50,8 +58,51 @@ export default function SlotMachinePage() {
     return () => clearInterval(interval);
   }, [updateBalance]);
 
+  // Renders a settled spin exactly as the chain recorded it in the player's SpinResult account
+  const showChainSpin = async () => {
+    if (!publicKey || !GAME_CONFIG) return;
+
+    try {
+      const program = new Program(idl as Idl, { connection } as AnchorProvider);
+      const gameConfig = new PublicKey(GAME_CONFIG);
+      const [spinResultAddress] = PublicKey.findProgramAddressSync(
+        [
+          new TextEncoder().encode('spin_result'),
+          gameConfig.toBytes(),
+          publicKey.toBytes(),
+          Uint8Array.from(new BN(spinNonce).toArray('le', 8)),
+        ],
+        program.programId
+      );
+      const spinResult = await program.account.spinResult.fetchNullable(spinResultAddress);
+      if (!spinResult) {
+        toast.error(`No spin result recorded for nonce ${spinNonce}`);
+        return;
+      }
+
+      // The grid is row-major (rows x reels), in the shape recorded at settlement; tier-mode
+      // spins have no grid to show
+      const grid: number[] = Array.from(spinResult.grid);
+      if (grid.length > 0) {
+        const reelCount = spinResult.reels;
+        const rows: string[][] = [];
+        for (let row = 0; row < spinResult.rows; row++) {
+          rows.push(grid.slice(row * reelCount, (row + 1) * reelCount).map((symbol) => SLOT_SYMBOLS[symbol] ?? '❔'));
+        }
+        setChainGrid(rows);
+      } else {
+        setChainGrid(null);
+      }
+      setLastResult(`${spinResult.tier} - ${spinResult.multiplier.toString()}x, paid ${(spinResult.payout.toNumber() / LAMPORTS_PER_SOL).toFixed(3)} SOL`);
+    } catch (error) {
+      console.error('Failed to load spin result:', error);
+      toast.error('Failed to load spin result');
+    }
+  };
+
   // Spin animation
   const animateReels = () => {
+    setChainGrid(null);
     setIsSpinning(true);
     
     let animationCount = 0;
@@ -295,13 +346,25 @@ export default function SlotMachinePage() {
         {isConnected ? (
           <>
             <div className="slot-machine">
-              <div className="reels">
-                {reels.map((symbol, index) => (
-                  <div key={index} className={`reel ${isSpinning ? 'spinning' : ''}`}>
-                    {symbol}
+              {chainGrid ? (
+                chainGrid.map((row, rowIndex) => (
+                  <div key={rowIndex} className="reels">
+                    {row.map((symbol, index) => (
+                      <div key={index} className="reel">
+                        {symbol}
+                      </div>
+                    ))}
                   </div>
-                ))}
-              </div>
+                ))
+              ) : (
+                <div className="reels">
+                  {reels.map((symbol, index) => (
+                    <div key={index} className={`reel ${isSpinning ? 'spinning' : ''}`}>
+                      {symbol}
+                    </div>
+                  ))}
+                </div>
+              )}
             </div>
 
             <div className="controls">
@@ -338,6 +401,28 @@ export default function SlotMachinePage() {
               >
                 Legacy Spin (Demo)
               </button>
+
+              {GAME_CONFIG && (
+                <div className="bet-section" style={{ marginTop: '10px' }}>
+                  <label htmlFor="spin-nonce">Settled spin nonce:</label>
+                  <input
+                    id="spin-nonce"
+                    type="number"
+                    min={0}
+                    value={spinNonce}
+                    onChange={(e) => setSpinNonce(e.target.value)}
+                    className="bet-input"
+                    disabled={gameState !== 'idle'}
+                  />
+                  <button
+                    onClick={showChainSpin}
+                    disabled={gameState !== 'idle' || spinNonce === ''}
+                    className="btn btn-secondary"
+                  >
+                    Show On-Chain Spin
+                  </button>
+                </div>
+              )}
             </div>
 
             {lastResult && (
@@ -373,6 +458,13 @@ export default function SlotMachinePage() {
               <p><strong>Legacy Method (Demo):</strong></p>
               <p>Click "Legacy Spin" for immediate results (less secure)</p>
               <br />
+              {GAME_CONFIG && (
+                <>
+                  <p><strong>On-Chain Spins:</strong></p>
+                  <p>Enter the nonce of a spin you settled with a spin result record to see its grid exactly as the chain decided it</p>
+                  <br />
+                </>
+              )}
               <p><strong>Payouts:</strong></p>
               <p>💎💎💎 - 25x (0.5% chance)</p>
               <p>7️⃣7️⃣7️⃣ - 10x (1.5% chance)</p>
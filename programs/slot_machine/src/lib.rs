@@ -1,7 +1,13 @@
+// Anchor 0.31 `#[program]` expansion still calls the deprecated `AccountInfo::realloc`
+#![allow(deprecated)]
+
 use anchor_lang::prelude::*;
 use anchor_lang::system_program::{self, Transfer as SystemTransfer};
-use anchor_spl::token::{Token, TokenAccount};
+use anchor_spl::token_2022::spl_token_2022::extension::{BaseStateWithExtensions, ExtensionType, StateWithExtensions};
+use anchor_spl::token_2022::spl_token_2022::state::Mint as MintState;
+use anchor_spl::token_interface::{self, Burn, Mint, MintTo, TokenAccount, TokenInterface, TransferChecked};
 use sha2::{Sha256, Digest};
+use switchboard_on_demand::{RandomnessAccountData, ON_DEMAND_DEVNET_PID, ON_DEMAND_MAINNET_PID};
 
 declare_id!("6rysbpjLEHyNinMSunnUX52G2a6N7sRWxLgVGAmhPU3Y"); // Replace with your program ID after first deployment
 
@@ -9,19 +15,243 @@ declare_id!("6rysbpjLEHyNinMSunnUX52G2a6N7sRWxLgVGAmhPU3Y"); // Replace with you
 const TREASURY_SEED: &[u8] = b"treasury";
 const COMMITMENT_SEED: &[u8] = b"commitment";
 const RANDOMNESS_SEED: &[u8] = b"randomness";
-const MIN_DELAY_SECONDS: i64 = 2; // Minimum 2 seconds between commit and reveal (reduced for testing)
-const MIN_BET_AMOUNT: u64 = 10_000_000; // 0.01 SOL in lamports
-const MAX_BET_AMOUNT: u64 = 1_000_000_000; // 1 SOL in lamports
+const PAYTABLE_SEED: &[u8] = b"paytable";
+const PLAYER_STATE_SEED: &[u8] = b"player_state";
+const JACKPOT_SEED: &[u8] = b"jackpot";
+const TOKEN_VAULT_SEED: &[u8] = b"token_vault";
+const LP_MINT_SEED: &[u8] = b"lp_mint";
+const SPIN_RESULT_SEED: &[u8] = b"spin_result";
+const GAME_STATS_SEED: &[u8] = b"game_stats";
+const LP_SHARE_DECIMALS: u8 = 9;
+const DEFAULT_MIN_DELAY_SLOTS: u64 = 4; // Minimum slots between commit and reveal; raise it with update_config on mainnet
+const MAX_MIN_DELAY_SLOTS: u64 = 9_000; // About an hour at 400ms slots; longer is a misconfiguration
+const DEFAULT_REVEAL_DEADLINE_SLOTS: u64 = 216_000; // Unrevealed commitments expire after about a day
+const SLOT_HASHES_WINDOW: u64 = 512; // SlotHashes only keeps the hashes of the most recent 512 slots
+const DEFAULT_MIN_BET_AMOUNT: u64 = 10_000_000; // 0.01 SOL in lamports
+const DEFAULT_MAX_BET_AMOUNT: u64 = 1_000_000_000; // 1 SOL in lamports
+const BPS_DENOMINATOR: u64 = 10_000;
+const MAX_ODDS_DENOMINATOR: u64 = 1_000_000_000; // Rolls are drawn from 1..=odds_denominator
+const MAX_PAYTABLE_TIERS: usize = 10;
+const MAX_TIER_LABEL_LEN: usize = 16;
+const MAX_REELS: usize = 5;
+const MAX_ROWS: usize = 3;
+const MAX_STRIP_LEN: usize = 32;
+const MAX_PAYLINES: usize = 10;
+const MAX_SYMBOL_PAYS: usize = 16;
+const MAX_SCATTER_PAYS: usize = 5;
+const MAX_JACKPOT_CONTRIBUTION_BPS: u16 = 1_000; // At most 10% of each bet feeds the jackpot
+const MAX_WITHDRAWAL_TIMELOCK_SECONDS: i64 = 30 * 24 * 60 * 60; // Longer locks would strand operator funds
+// discriminator + player + hash + bet_amount + timestamp + status + bump + nonce
+// + randomness_account + randomness_seed_slot + free_spin + max_payout + bet_mint + game_config + paytable_version
+// + commit_slot + use_switchboard + entropy_slot
+const COMMITMENT_SPACE: usize = 8 + 32 + 32 + 8 + 8 + 1 + 1 + 8 + 32 + 8 + 1 + 8 + 32 + 32 + 4 + 8 + 1 + 8;
+// discriminator + player + game_config + free_spins + free_spin_bet + bump + next_nonce
+// + total_wagered + total_won + spins + biggest_win + last_played
+const PLAYER_STATE_SPACE: usize = 8 + 32 + 32 + 4 + 8 + 1 + 8 + 8 + 8 + 8 + 8 + 8;
+// discriminator + player + game_config + nonce + commitment_hash + secret_value + salt + bet_amount
+// + bet_mint + slot + random_value + randomness_account + tier + multiplier + payout + jackpot_payout
+// + source + settled_at + bump + hash_slot + slot_hash + oracle_value + grid + rows + reels
+const SPIN_RESULT_SPACE: usize = 8 + 32 + 32 + 8 + 32 + 8 + 8 + 8 + 32 + 8 + 8 + 32 + (4 + MAX_TIER_LABEL_LEN)
+    + 8 + 8 + 8 + 1 + 8 + 1 + 8 + 32 + 32 + (4 + MAX_REELS * MAX_ROWS) + 1 + 1;
+// discriminator + game_config + bump + paytable_version + total_wagered + settled_wagered + total_paid
+// + jackpot_paid + total_refunded + spins + misses + tier_hits + outstanding_liability + realized_rtp_bps
+const GAME_STATS_SPACE: usize = 8 + 32 + 1 + 4 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 8 * MAX_PAYTABLE_TIERS + 8 + 8;
 
 #[program]
 pub mod slot_machine {
     use super::*;
 
-    // Initializes the game config and treasury vault
-    pub fn initialize(ctx: Context<Initialize>) -> Result<()> {
+    // Initializes a machine's game config and treasury vault. One authority can run several
+    // independent machines, each with its own treasury, told apart by `machine_id`.
+    pub fn initialize(ctx: Context<Initialize>, machine_id: u64) -> Result<()> {
         let game_config = &mut ctx.accounts.game_config;
         game_config.authority = *ctx.accounts.authority.key;
+        game_config.machine_id = machine_id;
         game_config.treasury_bump = ctx.bumps.treasury;
+        game_config.paytable = Pubkey::default(); // Set by create_paytable
+        game_config.jackpot_pool = Pubkey::default(); // Set by create_jackpot_pool
+        game_config.outstanding_liability = 0;
+        game_config.reveal_deadline_slots = DEFAULT_REVEAL_DEADLINE_SLOTS;
+        game_config.expiry_policy = ExpiryPolicy::Forfeit;
+        game_config.bet_mint = Pubkey::default(); // Native SOL until enable_token_betting
+        game_config.min_bet_amount = DEFAULT_MIN_BET_AMOUNT;
+        game_config.max_bet_amount = DEFAULT_MAX_BET_AMOUNT;
+        game_config.min_delay_slots = DEFAULT_MIN_DELAY_SLOTS;
+        game_config.guardian = Pubkey::default(); // Set by set_guardian
+        game_config.paused = false;
+        game_config.pending_authority = Pubkey::default(); // Set by propose_authority
+        // Pinned now so the game keeps its randomness client when either authority changes
+        game_config.randomness_client =
+            Pubkey::find_program_address(&[RANDOMNESS_SEED, ctx.accounts.authority.key.as_ref()], ctx.program_id).0;
+        game_config.withdrawal_timelock_seconds = 0; // Withdrawals execute immediately until set_withdrawal_timelock
+        game_config.pending_withdrawal_timelock_seconds = 0;
+        game_config.withdrawal_timelock_change_at = 0; // No timelock reduction queued
+        game_config.pending_withdrawal_amount = 0;
+        game_config.withdrawal_unlock_at = 0;
+        game_config.lp_mint = Pubkey::default(); // Set by create_liquidity_pool
+
+        let game_stats = &mut ctx.accounts.game_stats;
+        game_stats.game_config = game_config.key();
+        game_stats.bump = ctx.bumps.game_stats;
+        Ok(())
+    }
+
+    // Creates the paytable for a game and links it from the game config
+    pub fn create_paytable(
+        ctx: Context<CreatePaytable>,
+        tiers: Vec<PayoutTier>,
+        odds_denominator: u64,
+        max_rtp_bps: u16,
+    ) -> Result<()> {
+        let rtp_bps = validate_paytable(&tiers, odds_denominator, max_rtp_bps)?;
+
+        let paytable = &mut ctx.accounts.paytable;
+        paytable.game_config = ctx.accounts.game_config.key();
+        paytable.odds_denominator = odds_denominator;
+        paytable.max_rtp_bps = max_rtp_bps;
+        paytable.version = 1;
+        paytable.bump = ctx.bumps.paytable;
+        paytable.tiers = tiers;
+        paytable.reels = ReelConfig::default(); // Tier mode until configure_reels is called
+
+        ctx.accounts.game_config.paytable = paytable.key();
+
+        emit!(paytable_updated(paytable, rtp_bps));
+        emit!(config_updated(&ctx.accounts.game_config));
+        msg!("Paytable created with {} tiers, RTP: {} bps", paytable.tiers.len(), rtp_bps);
+        Ok(())
+    }
+
+    // Replaces the tiers, odds denominator and RTP ceiling of an existing paytable
+    pub fn update_paytable(
+        ctx: Context<UpdatePaytable>,
+        tiers: Vec<PayoutTier>,
+        odds_denominator: u64,
+        max_rtp_bps: u16,
+    ) -> Result<()> {
+        // Open commitments were placed at the current odds and must settle at them
+        require!(ctx.accounts.game_config.outstanding_liability == 0, SlotMachineError::PaytableLocked);
+        let rtp_bps = validate_paytable(&tiers, odds_denominator, max_rtp_bps)?;
+
+        let paytable = &mut ctx.accounts.paytable;
+        // Configured reels must also stay below a lowered ceiling
+        validate_reels(&paytable.reels, max_rtp_bps)?;
+        paytable.odds_denominator = odds_denominator;
+        paytable.max_rtp_bps = max_rtp_bps;
+        paytable.version = paytable.version.checked_add(1).ok_or(SlotMachineError::MathOverflow)?;
+        paytable.tiers = tiers;
+
+        emit!(paytable_updated(paytable, rtp_bps));
+        msg!("Paytable updated to version {} with {} tiers, RTP: {} bps", paytable.version, paytable.tiers.len(), rtp_bps);
+        Ok(())
+    }
+
+    // Configures reels, paylines, symbol pays and bonus features; empty strips switch the paytable back to tier mode
+    pub fn configure_reels(ctx: Context<UpdatePaytable>, reels: ReelConfig) -> Result<()> {
+        require!(ctx.accounts.game_config.outstanding_liability == 0, SlotMachineError::PaytableLocked);
+        let paytable = &mut ctx.accounts.paytable;
+        let rtp_bps = validate_reels(&reels, paytable.max_rtp_bps)?;
+
+        paytable.reels = reels;
+        paytable.version = paytable.version.checked_add(1).ok_or(SlotMachineError::MathOverflow)?;
+
+        emit!(paytable_updated(paytable, rtp_bps));
+        msg!(
+            "Reels configured: {} reels x {} rows, {} paylines, RTP: {} bps (paytable version {})",
+            paytable.reels.reel_strips.len(),
+            paytable.reels.rows,
+            paytable.reels.paylines.len(),
+            rtp_bps,
+            paytable.version
+        );
+        Ok(())
+    }
+
+    // Creates the progressive jackpot pool for a game, seeded by the authority
+    pub fn create_jackpot_pool(ctx: Context<CreateJackpotPool>, contribution_bps: u16, reseed_amount: u64) -> Result<()> {
+        require!(contribution_bps <= MAX_JACKPOT_CONTRIBUTION_BPS, SlotMachineError::InvalidJackpotConfig);
+        require!(!ctx.accounts.game_config.is_token_game(), SlotMachineError::JackpotRequiresSol);
+
+        // The authority funds the initial seed of the pool
+        if reseed_amount > 0 {
+            let cpi_accounts = SystemTransfer {
+                from: ctx.accounts.authority.to_account_info(),
+                to: ctx.accounts.jackpot_pool.to_account_info(),
+            };
+            let cpi_context = CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                cpi_accounts,
+            );
+            system_program::transfer(cpi_context, reseed_amount)?;
+        }
+
+        let jackpot_pool = &mut ctx.accounts.jackpot_pool;
+        jackpot_pool.game_config = ctx.accounts.game_config.key();
+        jackpot_pool.contribution_bps = contribution_bps;
+        jackpot_pool.reseed_amount = reseed_amount;
+        jackpot_pool.balance = reseed_amount;
+        jackpot_pool.hits = 0;
+        jackpot_pool.last_winner = Pubkey::default();
+        jackpot_pool.last_payout = 0;
+        jackpot_pool.bump = ctx.bumps.jackpot_pool;
+
+        ctx.accounts.game_config.jackpot_pool = jackpot_pool.key();
+
+        emit!(jackpot_pool_updated(jackpot_pool));
+        emit!(config_updated(&ctx.accounts.game_config));
+        msg!("Jackpot pool created. Contribution: {} bps, Seed: {} lamports", contribution_bps, reseed_amount);
+        Ok(())
+    }
+
+    // Changes the contribution rate and the amount left in the pool after a hit
+    pub fn update_jackpot_pool(ctx: Context<UpdateJackpotPool>, contribution_bps: u16, reseed_amount: u64) -> Result<()> {
+        require!(contribution_bps <= MAX_JACKPOT_CONTRIBUTION_BPS, SlotMachineError::InvalidJackpotConfig);
+
+        let jackpot_pool = &mut ctx.accounts.jackpot_pool;
+        jackpot_pool.contribution_bps = contribution_bps;
+        jackpot_pool.reseed_amount = reseed_amount;
+
+        emit!(jackpot_pool_updated(jackpot_pool));
+        msg!("Jackpot pool updated. Contribution: {} bps, Reseed: {} lamports", contribution_bps, reseed_amount);
+        Ok(())
+    }
+
+    // Switches a game to SPL token bets. Bets, payouts and refunds then move through a token vault
+    // owned by the treasury PDA instead of the treasury's lamports.
+    pub fn enable_token_betting(ctx: Context<EnableTokenBetting>) -> Result<()> {
+        let game_config = &mut ctx.accounts.game_config;
+
+        // Open commitments were reserved in SOL, so the currency can only be chosen on a quiet game
+        require!(!game_config.is_token_game(), SlotMachineError::BetCurrencyLocked);
+        require!(game_config.outstanding_liability == 0, SlotMachineError::BetCurrencyLocked);
+        require!(game_config.jackpot_pool == Pubkey::default(), SlotMachineError::JackpotRequiresSol);
+        // LP shares were priced in SOL
+        require!(game_config.lp_mint == Pubkey::default(), SlotMachineError::BetCurrencyLocked);
+        // Token games pay and withdraw from the vault only, so SOL left in the treasury would be stranded
+        let sol_bankroll = treasury_available(game_config, &ctx.accounts.treasury.to_account_info(), &None)?;
+        require!(sol_bankroll == 0, SlotMachineError::TreasuryNotEmpty);
+
+        // Token-2022 extensions that need extra accounts on every transfer or that let someone
+        // else move the vault's tokens are refused. Transfer fees and interest are supported.
+        let mint_info = ctx.accounts.bet_mint.to_account_info();
+        if *mint_info.owner == anchor_spl::token_2022::ID {
+            let mint_data = mint_info.try_borrow_data()?;
+            let mint_state = StateWithExtensions::<MintState>::unpack(&mint_data)?;
+            for extension in mint_state.get_extension_types()? {
+                require!(
+                    !matches!(
+                        extension,
+                        ExtensionType::TransferHook | ExtensionType::PermanentDelegate | ExtensionType::NonTransferable
+                    ),
+                    SlotMachineError::UnsupportedMintExtension
+                );
+            }
+        }
+
+        game_config.bet_mint = ctx.accounts.bet_mint.key();
+        emit!(config_updated(game_config));
+
+        msg!("Token betting enabled. Mint: {}, Vault: {}", game_config.bet_mint, ctx.accounts.token_vault.key());
         Ok(())
     }
 
@@ -31,16 +261,449 @@ pub mod slot_machine {
         randomness_client.authority = *ctx.accounts.authority.key;
         randomness_client.bump = ctx.bumps.randomness_client;
         randomness_client.use_switchboard = false; // Start with disabled, can be enabled later
+        randomness_client.pending_authority = Pubkey::default();
+        emit!(randomness_client_updated(randomness_client));
+        Ok(())
+    }
+
+    // Toggle between oracle-only (Switchboard) and fallback commit-reveal settlement
+    pub fn set_use_switchboard(ctx: Context<UpdateRandomnessClient>, use_switchboard: bool) -> Result<()> {
+        let randomness_client = &mut ctx.accounts.randomness_client;
+        randomness_client.use_switchboard = use_switchboard;
+        emit!(randomness_client_updated(randomness_client));
+        msg!("Switchboard oracle-only mode: {}", use_switchboard);
+        Ok(())
+    }
+
+    // First step of handing a machine to a new authority; default cancels a pending proposal
+    pub fn propose_authority(ctx: Context<UpdateConfig>, new_authority: Pubkey) -> Result<()> {
+        ctx.accounts.game_config.pending_authority = new_authority;
+        emit!(config_updated(&ctx.accounts.game_config));
+        msg!("Authority transfer proposed to {}", new_authority);
+        Ok(())
+    }
+
+    // Second step: the proposed authority signs to take over, so a mistyped key can't brick the machine
+    pub fn accept_authority(ctx: Context<AcceptAuthority>) -> Result<()> {
+        let game_config = &mut ctx.accounts.game_config;
+        game_config.authority = game_config.pending_authority;
+        game_config.pending_authority = Pubkey::default();
+        emit!(config_updated(game_config));
+        msg!("Authority of machine {} transferred to {}", game_config.machine_id, game_config.authority);
+        Ok(())
+    }
+
+    pub fn propose_randomness_authority(ctx: Context<UpdateRandomnessClient>, new_authority: Pubkey) -> Result<()> {
+        ctx.accounts.randomness_client.pending_authority = new_authority;
+        emit!(randomness_client_updated(&ctx.accounts.randomness_client));
+        msg!("Randomness client authority transfer proposed to {}", new_authority);
+        Ok(())
+    }
+
+    pub fn accept_randomness_authority(ctx: Context<AcceptRandomnessAuthority>) -> Result<()> {
+        let randomness_client = &mut ctx.accounts.randomness_client;
+        randomness_client.authority = randomness_client.pending_authority;
+        randomness_client.pending_authority = Pubkey::default();
+        emit!(randomness_client_updated(randomness_client));
+        msg!("Randomness client authority transferred to {}", randomness_client.authority);
+        Ok(())
+    }
+
+    // Sets how long a commitment may stay unrevealed and what happens to its bet afterwards
+    pub fn set_expiry_policy(
+        ctx: Context<UpdateConfig>,
+        reveal_deadline_slots: u64,
+        expiry_policy: ExpiryPolicy,
+    ) -> Result<()> {
+        let game_config = &mut ctx.accounts.game_config;
+
+        // The deadline has to leave room for the minimum reveal delay
+        require!(
+            reveal_deadline_slots > game_config.min_delay_slots,
+            SlotMachineError::InvalidExpiryConfig
+        );
+
+        game_config.reveal_deadline_slots = reveal_deadline_slots;
+        game_config.expiry_policy = expiry_policy;
+
+        emit!(config_updated(&ctx.accounts.game_config));
+        msg!("Expiry policy updated. Deadline: {} slots, Policy: {:?}", reveal_deadline_slots, expiry_policy);
+        Ok(())
+    }
+
+    // Names a second key that may pause and unpause the machine; default removes it
+    pub fn set_guardian(ctx: Context<UpdateConfig>, guardian: Pubkey) -> Result<()> {
+        ctx.accounts.game_config.guardian = guardian;
+        emit!(config_updated(&ctx.accounts.game_config));
+        msg!("Guardian set to {}", guardian);
+        Ok(())
+    }
+
+    // Emergency stop: new bets are refused while reveals and refunds keep working
+    pub fn pause(ctx: Context<SetPaused>) -> Result<()> {
+        ctx.accounts.game_config.paused = true;
+        emit!(config_updated(&ctx.accounts.game_config));
+        msg!("Machine {} paused by {}", ctx.accounts.game_config.machine_id, ctx.accounts.signer.key());
+        Ok(())
+    }
+
+    pub fn unpause(ctx: Context<SetPaused>) -> Result<()> {
+        ctx.accounts.game_config.paused = false;
+        emit!(config_updated(&ctx.accounts.game_config));
+        msg!("Machine {} unpaused by {}", ctx.accounts.game_config.machine_id, ctx.accounts.signer.key());
+        Ok(())
+    }
+
+    // Sets the bet limits and the minimum number of slots between commit and reveal of a machine
+    pub fn update_config(
+        ctx: Context<UpdateConfig>,
+        min_bet_amount: u64,
+        max_bet_amount: u64,
+        min_delay_slots: u64,
+    ) -> Result<()> {
+        let game_config = &mut ctx.accounts.game_config;
+
+        require!(min_bet_amount > 0 && min_bet_amount <= max_bet_amount, SlotMachineError::InvalidConfig);
+        // A reveal in the commit's own slot would mix in a slot hash the player could already see
+        require!(
+            (1..=MAX_MIN_DELAY_SLOTS).contains(&min_delay_slots),
+            SlotMachineError::InvalidConfig
+        );
+        require!(
+            min_delay_slots < game_config.reveal_deadline_slots,
+            SlotMachineError::InvalidExpiryConfig
+        );
+
+        game_config.min_bet_amount = min_bet_amount;
+        game_config.max_bet_amount = max_bet_amount;
+        game_config.min_delay_slots = min_delay_slots;
+
+        emit!(config_updated(game_config));
+        msg!(
+            "Config updated. Bets: {}..={}, Reveal delay: {} slots",
+            min_bet_amount,
+            max_bet_amount,
+            min_delay_slots
+        );
+        Ok(())
+    }
+
+    // Adds bankroll to the treasury (or token vault). Anyone may deposit
+    pub fn deposit_treasury(ctx: Context<DepositTreasury>, amount: u64) -> Result<()> {
+        let token_accounts = treasury_token_accounts(
+            &ctx.accounts.game_config,
+            &ctx.accounts.bet_mint,
+            &ctx.accounts.token_vault,
+            &ctx.accounts.depositor_token_account,
+            &ctx.accounts.token_program,
+        )?;
+        deposit_to_treasury(
+            ctx.accounts.treasury.to_account_info(),
+            ctx.accounts.depositor.to_account_info(),
+            token_accounts,
+            ctx.accounts.system_program.to_account_info(),
+            amount,
+        )?;
+
+        emit!(TreasuryDeposited {
+            game_config: ctx.accounts.game_config.key(),
+            depositor: ctx.accounts.depositor.key(),
+            bet_mint: ctx.accounts.game_config.bet_mint,
+            amount,
+        });
+        msg!("Treasury deposit: {} from {}", amount, ctx.accounts.depositor.key());
+        Ok(())
+    }
+
+    // Sets the delay between request_withdrawal and withdraw_treasury; 0 disables the lock.
+    // Increases apply at once. A reduction only applies after the current delay has passed,
+    // otherwise the authority could drop the lock and withdraw in the same transaction.
+    pub fn set_withdrawal_timelock(ctx: Context<UpdateConfig>, withdrawal_timelock_seconds: i64) -> Result<()> {
+        require!(
+            (0..=MAX_WITHDRAWAL_TIMELOCK_SECONDS).contains(&withdrawal_timelock_seconds),
+            SlotMachineError::InvalidConfig
+        );
+        let now = Clock::get()?.unix_timestamp;
+        let game_config = &mut ctx.accounts.game_config;
+        game_config.apply_timelock_change(now);
+
+        // A pending withdrawal keeps the unlock time it was requested with
+        if withdrawal_timelock_seconds >= game_config.withdrawal_timelock_seconds {
+            game_config.withdrawal_timelock_seconds = withdrawal_timelock_seconds;
+            game_config.pending_withdrawal_timelock_seconds = 0;
+            game_config.withdrawal_timelock_change_at = 0;
+            msg!("Withdrawal timelock set to {}s", withdrawal_timelock_seconds);
+        } else {
+            game_config.pending_withdrawal_timelock_seconds = withdrawal_timelock_seconds;
+            game_config.withdrawal_timelock_change_at = now
+                .checked_add(game_config.withdrawal_timelock_seconds)
+                .ok_or(SlotMachineError::MathOverflow)?;
+            msg!(
+                "Withdrawal timelock reduction to {}s queued, applies at {}",
+                withdrawal_timelock_seconds,
+                game_config.withdrawal_timelock_change_at
+            );
+        }
+
+        emit!(config_updated(&ctx.accounts.game_config));
+        Ok(())
+    }
+
+    // Queues a withdrawal for the authority; 0 cancels the pending one
+    pub fn request_withdrawal(ctx: Context<UpdateConfig>, amount: u64) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let game_config = &mut ctx.accounts.game_config;
+        game_config.apply_timelock_change(now);
+        game_config.pending_withdrawal_amount = amount;
+        game_config.withdrawal_unlock_at = now
+            .checked_add(game_config.withdrawal_timelock_seconds)
+            .ok_or(SlotMachineError::MathOverflow)?;
+
+        // Players and LPs get the whole lock period to react to a pending withdrawal
+        emit!(WithdrawalRequested {
+            game_config: game_config.key(),
+            authority: game_config.authority,
+            amount,
+            unlock_at: game_config.withdrawal_unlock_at,
+        });
+        msg!("Withdrawal of {} requested, unlocks at {}", amount, game_config.withdrawal_unlock_at);
+        Ok(())
+    }
+
+    // Pays out the pending withdrawal once unlocked, never touching funds reserved for open
+    // commitments or the treasury's rent-exempt minimum
+    pub fn withdraw_treasury(ctx: Context<WithdrawTreasury>) -> Result<()> {
+        // Once outside LPs share the bankroll, the operator exits through withdraw_liquidity like them
+        require!(ctx.accounts.game_config.lp_mint == Pubkey::default(), SlotMachineError::LiquidityPoolActive);
+        let amount = ctx.accounts.game_config.pending_withdrawal_amount;
+        require!(amount > 0, SlotMachineError::NoPendingWithdrawal);
+        require!(
+            Clock::get()?.unix_timestamp >= ctx.accounts.game_config.withdrawal_unlock_at,
+            SlotMachineError::WithdrawalLocked
+        );
+
+        // Balance is checked at execution, since bets placed during the lock add liability
+        let available = treasury_available(
+            &ctx.accounts.game_config,
+            &ctx.accounts.treasury.to_account_info(),
+            &ctx.accounts.token_vault,
+        )?;
+        let withdrawable = available.saturating_sub(ctx.accounts.game_config.outstanding_liability);
+        require!(amount <= withdrawable, SlotMachineError::InsufficientTreasury);
+
+        let token_accounts = treasury_token_accounts(
+            &ctx.accounts.game_config,
+            &ctx.accounts.bet_mint,
+            &ctx.accounts.token_vault,
+            &ctx.accounts.authority_token_account,
+            &ctx.accounts.token_program,
+        )?;
+        pay_from_treasury(
+            &ctx.accounts.game_config,
+            ctx.accounts.treasury.to_account_info(),
+            ctx.accounts.authority.to_account_info(),
+            token_accounts,
+            ctx.accounts.system_program.to_account_info(),
+            amount,
+        )?;
+
+        let game_config = &mut ctx.accounts.game_config;
+        game_config.pending_withdrawal_amount = 0;
+        game_config.withdrawal_unlock_at = 0;
+
+        emit!(TreasuryWithdrawn {
+            game_config: game_config.key(),
+            authority: ctx.accounts.authority.key(),
+            bet_mint: game_config.bet_mint,
+            amount,
+        });
+        msg!("Treasury withdrawal: {} to {}", amount, ctx.accounts.authority.key());
+        Ok(())
+    }
+
+    // Creates the LP share mint of a game. The treasury PDA is the mint authority, so shares
+    // can only be issued against deposits
+    pub fn create_liquidity_pool(ctx: Context<CreateLiquidityPool>) -> Result<()> {
+        let game_config = &mut ctx.accounts.game_config;
+        require!(game_config.lp_mint == Pubkey::default(), SlotMachineError::LiquidityPoolActive);
+        game_config.lp_mint = ctx.accounts.lp_mint.key();
+
+        emit!(config_updated(game_config));
+        msg!("Liquidity pool created. Share mint: {}", game_config.lp_mint);
+        Ok(())
+    }
+
+    // Deposits bankroll and mints shares at the treasury's current NAV. House PnL from settled
+    // spins moves the NAV, so it accrues to share holders without any bookkeeping
+    pub fn deposit_liquidity(ctx: Context<DepositLiquidity>, amount: u64) -> Result<()> {
+        require!(!ctx.accounts.game_config.paused, SlotMachineError::MachinePaused);
+        // The NAV only prices shares fairly once every open bet has settled; otherwise a player
+        // who already knows their outcome could trade shares against it
+        require!(ctx.accounts.game_config.outstanding_liability == 0, SlotMachineError::LiquidityLocked);
+
+        let share_supply = ctx.accounts.lp_mint.supply;
+        // The first deposit also takes ownership of whatever bankroll is already in the treasury,
+        // so only the operator may seed an empty pool
+        if share_supply == 0 {
+            require!(
+                ctx.accounts.depositor.key() == ctx.accounts.game_config.authority,
+                SlotMachineError::Unauthorized
+            );
+        }
+
+        let nav_before = treasury_available(
+            &ctx.accounts.game_config,
+            &ctx.accounts.treasury.to_account_info(),
+            &ctx.accounts.token_vault,
+        )?;
+        let token_accounts = treasury_token_accounts(
+            &ctx.accounts.game_config,
+            &ctx.accounts.bet_mint,
+            &ctx.accounts.token_vault,
+            &ctx.accounts.depositor_token_account,
+            &ctx.accounts.token_program,
+        )?;
+        deposit_to_treasury(
+            ctx.accounts.treasury.to_account_info(),
+            ctx.accounts.depositor.to_account_info(),
+            token_accounts,
+            ctx.accounts.system_program.to_account_info(),
+            amount,
+        )?;
+        if let Some(token_vault) = ctx.accounts.token_vault.as_mut() {
+            token_vault.reload()?;
+        }
+        let nav_after = treasury_available(
+            &ctx.accounts.game_config,
+            &ctx.accounts.treasury.to_account_info(),
+            &ctx.accounts.token_vault,
+        )?;
+        // Transfer-fee mints deliver less than the amount sent
+        let received = nav_after.checked_sub(nav_before).ok_or(SlotMachineError::MathOverflow)?;
+
+        let shares = if share_supply == 0 {
+            nav_after
+        } else {
+            // A pool whose bankroll was lost entirely can't price new shares
+            let shares = (received as u128 * share_supply as u128)
+                .checked_div(nav_before as u128)
+                .ok_or(SlotMachineError::InsufficientTreasury)?;
+            u64::try_from(shares).map_err(|_| SlotMachineError::MathOverflow)?
+        };
+        require!(shares > 0, SlotMachineError::LiquidityTooSmall);
+
+        let game_config_key = ctx.accounts.game_config.key();
+        let treasury_bump = [ctx.accounts.game_config.treasury_bump];
+        let treasury_seeds: &[&[u8]] = &[TREASURY_SEED, game_config_key.as_ref(), &treasury_bump];
+        let signer_seeds: &[&[&[u8]]] = &[treasury_seeds];
+        let mint_context = CpiContext::new_with_signer(
+            ctx.accounts.lp_token_program.to_account_info(),
+            MintTo {
+                mint: ctx.accounts.lp_mint.to_account_info(),
+                to: ctx.accounts.depositor_lp_account.to_account_info(),
+                authority: ctx.accounts.treasury.to_account_info(),
+            },
+            signer_seeds,
+        );
+        token_interface::mint_to(mint_context, shares)?;
+
+        emit!(LiquidityDeposited {
+            game_config: game_config_key,
+            provider: ctx.accounts.depositor.key(),
+            amount: received,
+            shares,
+            nav: nav_after,
+        });
+        msg!("Liquidity deposit: {} for {} shares (NAV {})", received, shares, nav_after);
+        Ok(())
+    }
+
+    // Burns shares for their pro-rata cut of the NAV, only while no commitments are open
+    pub fn withdraw_liquidity(ctx: Context<WithdrawLiquidity>, shares: u64) -> Result<()> {
+        require!(ctx.accounts.game_config.outstanding_liability == 0, SlotMachineError::LiquidityLocked);
+        let share_supply = ctx.accounts.lp_mint.supply;
+        require!(shares > 0 && shares <= share_supply, SlotMachineError::LiquidityTooSmall);
+
+        let nav = treasury_available(
+            &ctx.accounts.game_config,
+            &ctx.accounts.treasury.to_account_info(),
+            &ctx.accounts.token_vault,
+        )?;
+        let amount = u64::try_from(shares as u128 * nav as u128 / share_supply as u128)
+            .map_err(|_| SlotMachineError::MathOverflow)?;
+        require!(amount > 0, SlotMachineError::LiquidityTooSmall);
+
+        let burn_context = CpiContext::new(
+            ctx.accounts.lp_token_program.to_account_info(),
+            Burn {
+                mint: ctx.accounts.lp_mint.to_account_info(),
+                from: ctx.accounts.provider_lp_account.to_account_info(),
+                authority: ctx.accounts.provider.to_account_info(),
+            },
+        );
+        token_interface::burn(burn_context, shares)?;
+
+        let token_accounts = treasury_token_accounts(
+            &ctx.accounts.game_config,
+            &ctx.accounts.bet_mint,
+            &ctx.accounts.token_vault,
+            &ctx.accounts.provider_token_account,
+            &ctx.accounts.token_program,
+        )?;
+        pay_from_treasury(
+            &ctx.accounts.game_config,
+            ctx.accounts.treasury.to_account_info(),
+            ctx.accounts.provider.to_account_info(),
+            token_accounts,
+            ctx.accounts.system_program.to_account_info(),
+            amount,
+        )?;
+
+        emit!(LiquidityWithdrawn {
+            game_config: ctx.accounts.game_config.key(),
+            provider: ctx.accounts.provider.key(),
+            amount,
+            shares,
+            nav: nav - amount,
+        });
+        msg!("Liquidity withdrawal: {} shares for {}", shares, amount);
         Ok(())
     }
 
     // Phase 1: Player commits to a secret random value
     pub fn commit(ctx: Context<Commit>, commitment_hash: [u8; 32], bet_amount: u64, nonce: u64) -> Result<()> {
+        require!(!ctx.accounts.game_config.paused, SlotMachineError::MachinePaused);
+        require!(!ctx.accounts.game_config.is_token_game(), SlotMachineError::WrongBetCurrency);
+
         // Validate bet amount is within allowed range
-        require!(bet_amount >= MIN_BET_AMOUNT, SlotMachineError::BetTooLow);
-        require!(bet_amount <= MAX_BET_AMOUNT, SlotMachineError::BetTooHigh);
+        require!(bet_amount >= ctx.accounts.game_config.min_bet_amount, SlotMachineError::BetTooLow);
+        require!(bet_amount <= ctx.accounts.game_config.max_bet_amount, SlotMachineError::BetTooHigh);
+        require_jackpot_pool(&ctx.accounts.game_config, &ctx.accounts.jackpot_pool)?;
+
+        // Settled commitments are closed, so the nonce is what keeps their seeds from being reused
+        let player_state = &mut ctx.accounts.player_state;
+        player_state.initialize_if_needed(ctx.accounts.player.key(), ctx.accounts.game_config.key(), ctx.bumps.player_state);
+        player_state.use_nonce(nonce)?;
+        player_state.record_wager(bet_amount, Clock::get()?.unix_timestamp)?;
+
+        // Feed the progressive jackpot with its share of the bet
+        let contribution = jackpot_contribution(&ctx.accounts.jackpot_pool, bet_amount)?;
+        if let Some(jackpot_pool) = ctx.accounts.jackpot_pool.as_mut() {
+            if contribution > 0 {
+                let cpi_accounts = SystemTransfer {
+                    from: ctx.accounts.player.to_account_info(),
+                    to: jackpot_pool.to_account_info(),
+                };
+                let cpi_context = CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    cpi_accounts,
+                );
+                system_program::transfer(cpi_context, contribution)?;
+                jackpot_pool.balance = jackpot_pool.balance.checked_add(contribution).ok_or(SlotMachineError::MathOverflow)?;
+            }
+        }
 
-        // Transfer the bet from the player to the treasury (locked until reveal)
+        // Transfer the rest of the bet from the player to the treasury (locked until reveal)
         let cpi_accounts = SystemTransfer {
             from: ctx.accounts.player.to_account_info(),
             to: ctx.accounts.treasury.to_account_info(),
@@ -49,52 +712,221 @@ pub mod slot_machine {
             ctx.accounts.system_program.to_account_info(),
             cpi_accounts,
         );
-        system_program::transfer(cpi_context, bet_amount)?;
+        system_program::transfer(cpi_context, bet_amount - contribution)?;
+
+        // Reserve the worst-case payout so the treasury can always settle this commitment
+        let max_payout = bet_amount
+            .checked_mul(ctx.accounts.paytable.max_multiplier()?)
+            .ok_or(SlotMachineError::MathOverflow)?;
+        let available = treasury_available(&ctx.accounts.game_config, &ctx.accounts.treasury.to_account_info(), &None)?;
+        reserve_liability(&mut ctx.accounts.game_config, available, max_payout)?;
+        let outstanding_liability = ctx.accounts.game_config.outstanding_liability;
+        ctx.accounts.game_stats.record_bet(bet_amount, outstanding_liability)?;
 
         // Store the commitment
         let commitment = &mut ctx.accounts.commitment;
         commitment.player = *ctx.accounts.player.key;
         commitment.commitment_hash = commitment_hash;
         commitment.bet_amount = bet_amount;
-        commitment.timestamp = Clock::get()?.unix_timestamp;
-        commitment.revealed = false;
+        let clock = Clock::get()?;
+        commitment.timestamp = clock.unix_timestamp;
+        commitment.commit_slot = clock.slot;
+        commitment.entropy_slot = clock.slot + ctx.accounts.game_config.min_delay_slots;
+        commitment.status = CommitmentStatus::Committed;
         commitment.bump = ctx.bumps.commitment;
         commitment.nonce = nonce;
-        commitment.randomness_requested = false;
+        commitment.randomness_account = Pubkey::default();
+        commitment.randomness_seed_slot = 0;
+        commitment.free_spin = false;
+        commitment.max_payout = max_payout;
+        commitment.bet_mint = Pubkey::default();
+        commitment.game_config = ctx.accounts.game_config.key();
+        commitment.paytable_version = ctx.accounts.paytable.version;
+        commitment.use_switchboard = ctx.accounts.randomness_client.use_switchboard;
 
+        emit!(bet_committed(commitment));
         msg!("Commitment stored. Hash: {:?}, Bet: {} lamports", commitment_hash, bet_amount);
         Ok(())
     }
 
-    // Request randomness from Switchboard (simplified placeholder)
+    // Phase 1 (SPL token): same as commit, with the bet moved into the game's token vault
+    pub fn commit_token(ctx: Context<CommitToken>, commitment_hash: [u8; 32], bet_amount: u64, nonce: u64) -> Result<()> {
+        require!(!ctx.accounts.game_config.paused, SlotMachineError::MachinePaused);
+        require!(ctx.accounts.game_config.is_token_game(), SlotMachineError::WrongBetCurrency);
+
+        let player_state = &mut ctx.accounts.player_state;
+        player_state.initialize_if_needed(ctx.accounts.player.key(), ctx.accounts.game_config.key(), ctx.bumps.player_state);
+        player_state.use_nonce(nonce)?;
+
+        // Transfer the bet from the player's token account to the vault
+        let vault_before = ctx.accounts.token_vault.amount;
+        let cpi_accounts = TransferChecked {
+            from: ctx.accounts.player_token_account.to_account_info(),
+            mint: ctx.accounts.bet_mint.to_account_info(),
+            to: ctx.accounts.token_vault.to_account_info(),
+            authority: ctx.accounts.player.to_account_info(),
+        };
+        let cpi_context = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            cpi_accounts,
+        );
+        token_interface::transfer_checked(cpi_context, bet_amount, ctx.accounts.bet_mint.decimals)?;
+        ctx.accounts.token_vault.reload()?;
+
+        // Transfer-fee mints withhold part of the transfer, so the bet is what the vault received
+        let received = ctx.accounts.token_vault.amount
+            .checked_sub(vault_before)
+            .ok_or(SlotMachineError::MathOverflow)?;
+
+        // Validate bet amount is within allowed range (in the mint's base units)
+        require!(received >= ctx.accounts.game_config.min_bet_amount, SlotMachineError::BetTooLow);
+        require!(received <= ctx.accounts.game_config.max_bet_amount, SlotMachineError::BetTooHigh);
+        ctx.accounts.player_state.record_wager(received, Clock::get()?.unix_timestamp)?;
+
+        // Reserve the worst-case payout so the vault can always settle this commitment
+        let max_payout = received
+            .checked_mul(ctx.accounts.paytable.max_multiplier()?)
+            .ok_or(SlotMachineError::MathOverflow)?;
+        let available = ctx.accounts.token_vault.amount;
+        reserve_liability(&mut ctx.accounts.game_config, available, max_payout)?;
+        let outstanding_liability = ctx.accounts.game_config.outstanding_liability;
+        ctx.accounts.game_stats.record_bet(received, outstanding_liability)?;
+
+        // Store the commitment
+        let commitment = &mut ctx.accounts.commitment;
+        commitment.player = *ctx.accounts.player.key;
+        commitment.commitment_hash = commitment_hash;
+        commitment.bet_amount = received;
+        let clock = Clock::get()?;
+        commitment.timestamp = clock.unix_timestamp;
+        commitment.commit_slot = clock.slot;
+        commitment.entropy_slot = clock.slot + ctx.accounts.game_config.min_delay_slots;
+        commitment.status = CommitmentStatus::Committed;
+        commitment.bump = ctx.bumps.commitment;
+        commitment.nonce = nonce;
+        commitment.randomness_account = Pubkey::default();
+        commitment.randomness_seed_slot = 0;
+        commitment.free_spin = false;
+        commitment.max_payout = max_payout;
+        commitment.bet_mint = ctx.accounts.game_config.bet_mint;
+        commitment.game_config = ctx.accounts.game_config.key();
+        commitment.paytable_version = ctx.accounts.paytable.version;
+        commitment.use_switchboard = ctx.accounts.randomness_client.use_switchboard;
+
+        emit!(bet_committed(commitment));
+        msg!("Token commitment stored. Hash: {:?}, Bet: {} tokens ({} sent)", commitment_hash, received, bet_amount);
+        Ok(())
+    }
+
+    // Phase 1 (free spin): commit using a free spin credited by a scatter bonus, without a new bet
+    pub fn commit_free_spin(ctx: Context<CommitFreeSpin>, commitment_hash: [u8; 32], nonce: u64) -> Result<()> {
+        require!(!ctx.accounts.game_config.paused, SlotMachineError::MachinePaused);
+        let player_state = &mut ctx.accounts.player_state;
+        require!(player_state.free_spins > 0, SlotMachineError::NoFreeSpins);
+        player_state.use_nonce(nonce)?;
+        player_state.free_spins -= 1;
+        player_state.last_played = Clock::get()?.unix_timestamp; // Free spins wager nothing
+
+        // Free-spin wins are paid from the treasury under the same solvency rules as paid bets
+        let max_payout = player_state.free_spin_bet
+            .checked_mul(ctx.accounts.paytable.max_multiplier()?)
+            .ok_or(SlotMachineError::MathOverflow)?;
+        let available = treasury_available(
+            &ctx.accounts.game_config,
+            &ctx.accounts.treasury.to_account_info(),
+            &ctx.accounts.token_vault,
+        )?;
+        reserve_liability(&mut ctx.accounts.game_config, available, max_payout)?;
+        let outstanding_liability = ctx.accounts.game_config.outstanding_liability;
+        ctx.accounts.game_stats.record_bet(0, outstanding_liability)?;
+
+        // Store the commitment at the bet level the free spins were awarded at
+        let commitment = &mut ctx.accounts.commitment;
+        commitment.player = *ctx.accounts.player.key;
+        commitment.commitment_hash = commitment_hash;
+        commitment.bet_amount = player_state.free_spin_bet;
+        let clock = Clock::get()?;
+        commitment.timestamp = clock.unix_timestamp;
+        commitment.commit_slot = clock.slot;
+        commitment.entropy_slot = clock.slot + ctx.accounts.game_config.min_delay_slots;
+        commitment.status = CommitmentStatus::Committed;
+        commitment.bump = ctx.bumps.commitment;
+        commitment.nonce = nonce;
+        commitment.randomness_account = Pubkey::default();
+        commitment.randomness_seed_slot = 0;
+        commitment.free_spin = true;
+        commitment.max_payout = max_payout;
+        commitment.bet_mint = ctx.accounts.game_config.bet_mint;
+        commitment.game_config = ctx.accounts.game_config.key();
+        commitment.paytable_version = ctx.accounts.paytable.version;
+        commitment.use_switchboard = ctx.accounts.randomness_client.use_switchboard;
+
+        emit!(bet_committed(commitment));
+        msg!(
+            "Free spin commitment stored. Hash: {:?}, Bet: {}, Free spins left: {}",
+            commitment_hash,
+            commitment.bet_amount,
+            player_state.free_spins
+        );
+        Ok(())
+    }
+
+    // Bind a Switchboard on-demand randomness account to the commitment
     pub fn request_randomness(ctx: Context<RequestRandomness>) -> Result<()> {
+        // The oracle path is only open to commitments placed in Switchboard mode
         let commitment = &mut ctx.accounts.commitment;
+        require!(commitment.use_switchboard, SlotMachineError::SwitchboardDisabled);
         
-        // Verify the commitment hasn't been revealed already
-        require!(!commitment.revealed, SlotMachineError::AlreadyRevealed);
+        // Only a fresh commitment can choose the oracle path, and only once
+        commitment.require_status(CommitmentStatus::Committed)?;
         
         // Verify minimum delay has passed
-        let current_time = Clock::get()?.unix_timestamp;
-        let time_elapsed = current_time - commitment.timestamp;
-        require!(time_elapsed >= MIN_DELAY_SECONDS, SlotMachineError::InsufficientDelay);
+        let clock = Clock::get()?;
+        let slots_elapsed = clock.slot.saturating_sub(commitment.commit_slot);
+        require!(slots_elapsed >= ctx.accounts.game_config.min_delay_slots, SlotMachineError::InsufficientDelay);
+
+        let randomness_data = RandomnessAccountData::parse(ctx.accounts.randomness_account_data.data.borrow())
+            .map_err(|_| SlotMachineError::InvalidRandomnessAccount)?;
+
+        // The randomness must have been committed in the previous slot, so the player
+        // cannot shop around for an account whose future value is already known
+        require!(
+            randomness_data.seed_slot == clock.slot.saturating_sub(1),
+            SlotMachineError::RandomnessExpired
+        );
 
-        // Verify randomness hasn't been requested yet for this commitment
-        require!(!commitment.randomness_requested, SlotMachineError::RandomnessAlreadyRequested);
+        // The randomness must not be revealed yet
+        require!(
+            randomness_data.get_value(&clock).is_err(),
+            SlotMachineError::RandomnessAlreadyRevealed
+        );
 
-        // Mark randomness as requested
-        commitment.randomness_requested = true;
+        // Bind the randomness account to the commitment
+        commitment.status = CommitmentStatus::RandomnessRequested;
+        commitment.randomness_account = ctx.accounts.randomness_account_data.key();
+        commitment.randomness_seed_slot = randomness_data.seed_slot;
 
-        // In a full implementation, this would integrate with Switchboard on-demand randomness
-        // For now, we'll use the fallback method
-        msg!("Randomness request queued (using fallback implementation)");
-        msg!("In production, this would integrate with Switchboard on-demand randomness");
+        emit!(RandomnessRequested {
+            player: commitment.player,
+            game_config: commitment.game_config,
+            commitment: commitment.key(),
+            randomness_account: commitment.randomness_account,
+            seed_slot: commitment.randomness_seed_slot,
+        });
+        msg!(
+            "Randomness requested. Account: {}, Seed slot: {}",
+            commitment.randomness_account,
+            commitment.randomness_seed_slot
+        );
         
         Ok(())
     }
 
-    // Consume randomness and execute slot machine logic (Switchboard callback)
+    // Consume the revealed Switchboard randomness and execute slot machine logic
     pub fn consume_randomness(ctx: Context<ConsumeRandomness>, secret_value: u64, salt: u64) -> Result<()> {
+        // The oracle path is only open to commitments placed in Switchboard mode
         let commitment = &mut ctx.accounts.commitment;
+        require!(commitment.use_switchboard, SlotMachineError::SwitchboardDisabled);
         
         // Verify the commitment matches the revealed values
         let mut hasher = Sha256::new();
@@ -108,92 +940,98 @@ pub mod slot_machine {
             SlotMachineError::InvalidReveal
         );
 
-        // Verify randomness was requested
-        require!(commitment.randomness_requested, SlotMachineError::RandomnessNotRequested);
-
-        // Mark as revealed
-        commitment.revealed = true;
+        // Verify randomness was requested and the commitment has not been settled since
+        commitment.require_status(CommitmentStatus::RandomnessRequested)?;
 
-        // In a full Switchboard implementation, we would get randomness from the callback
-        // For now, we'll simulate high-quality randomness
+        // Read the oracle value revealed for the seed slot recorded at request time
         let clock = Clock::get()?;
+        require!(
+            !commitment.is_expired(&ctx.accounts.game_config, clock.slot),
+            SlotMachineError::CommitmentExpired
+        );
+        let randomness_data = RandomnessAccountData::parse(ctx.accounts.randomness_account_data.data.borrow())
+            .map_err(|_| SlotMachineError::InvalidRandomnessAccount)?;
+        require!(
+            randomness_data.seed_slot == commitment.randomness_seed_slot,
+            SlotMachineError::RandomnessExpired
+        );
+        let switchboard_randomness = randomness_data
+            .get_value(&clock)
+            .map_err(|_| SlotMachineError::RandomnessNotResolved)?;
+
+        require!(commitment.bet_mint == ctx.accounts.game_config.bet_mint, SlotMachineError::WrongBetCurrency);
+        // Odds are fixed at commit time; the paytable is locked while commitments are open
+        require!(
+            commitment.paytable_version == ctx.accounts.paytable.version,
+            SlotMachineError::PaytableChanged
+        );
+
+        // Release the reserved payout; the commitment is closed when the instruction returns
+        release_liability(&mut ctx.accounts.game_config, commitment.max_payout);
+
         let mut final_hasher = Sha256::new();
         
-        // Combine multiple entropy sources for maximum security
+        // Combine the oracle value with the player's committed entropy
+        final_hasher.update(switchboard_randomness); // Switchboard revealed value
         final_hasher.update(secret_value.to_le_bytes()); // Player secret
         final_hasher.update(salt.to_le_bytes()); // Player salt
-        final_hasher.update(clock.slot.to_le_bytes()); // Network slot
-        final_hasher.update(clock.unix_timestamp.to_le_bytes()); // Current time
         final_hasher.update(commitment.timestamp.to_le_bytes()); // Commitment time
         final_hasher.update(ctx.accounts.player.key().as_ref()); // Player key
         final_hasher.update(commitment.bet_amount.to_le_bytes()); // Bet amount
-        // In real Switchboard implementation, we'd add: final_hasher.update(&switchboard_randomness);
         
-        let final_hash = final_hasher.finalize();
-        let random_bytes = u64::from_le_bytes(
-            final_hash[0..8].try_into().unwrap()
-        );
-        let secure_random = (random_bytes % 100) + 1;
-
-        msg!("Enhanced secure random value: {} (with Switchboard integration ready)", secure_random);
-
-        // Execute slot machine logic with secure randomness
-        let payout_tiers = [
-            (99.5, 25, "JACKPOT!"),     // 0.5% chance for 25x
-            (98.0, 10, "Big Win!"),     // 1.5% chance for 10x
-            (95.0, 6,  "Great!"),       // 3%   chance for 6x
-            (90.0, 3,  "Nice!"),        // 5%   chance for 3x
-            (80.0, 2,  "Win!"),         // 10%  chance for 2x
-            (65.0, 1,  "Break Even"),   // 15%  chance for 1x
-        ];
-
-        let (payout_multiplier, win_message) = payout_tiers
-            .iter()
-            .find(|(threshold, _, _)| secure_random > *threshold as u64)
-            .map(|(_, multiplier, message)| (*multiplier, *message))
-            .unwrap_or((0, "Try Again!"));
-            
-        let win = payout_multiplier > 0;
-
-        if win {
-            let payout = commitment.bet_amount * payout_multiplier;
-            let treasury_bump = [ctx.accounts.game_config.treasury_bump];
-            let treasury_seeds: &[&[u8]] = &[TREASURY_SEED, &treasury_bump];
-            let signer_seeds: &[&[&[u8]]] = &[treasury_seeds];
-            
-            let transfer_accounts = SystemTransfer {
-                from: ctx.accounts.treasury.to_account_info(),
-                to: ctx.accounts.player.to_account_info(),
-            };
-            let transfer_ctx = CpiContext::new_with_signer(
-                ctx.accounts.system_program.to_account_info(),
-                transfer_accounts,
-                signer_seeds,
-            );
-            system_program::transfer(transfer_ctx, payout)?;
+        let final_hash: [u8; 32] = final_hasher.finalize().into();
+        let outcome = ctx.accounts.paytable.resolve(final_hash)?;
+        msg!("Switchboard secure random value: {}", outcome.roll);
 
-            msg!("{} You won! Payout: {} lamports ({}x multiplier)", win_message, payout, payout_multiplier);
-        } else {
-            msg!("{} Better luck next time!", win_message);
+        // Execute slot machine logic with secure randomness against the on-chain paytable/reels
+        let settled = settle_outcome(
+            SettlementAccounts {
+                game_config: &ctx.accounts.game_config,
+                game_stats: &mut ctx.accounts.game_stats,
+                paytable: &ctx.accounts.paytable,
+                treasury: &ctx.accounts.treasury,
+                player: &ctx.accounts.player,
+                jackpot_pool: &mut ctx.accounts.jackpot_pool,
+                player_state: &mut ctx.accounts.player_state,
+                player_state_bump: ctx.bumps.player_state,
+                bet_mint: &ctx.accounts.bet_mint,
+                token_vault: &ctx.accounts.token_vault,
+                player_token_account: &ctx.accounts.player_token_account,
+                token_program: &ctx.accounts.token_program,
+                system_program: &ctx.accounts.system_program,
+            },
+            SettledBet::of(commitment, SettlementSource::Switchboard),
+            &outcome,
+        )?;
+        if let (Some(spin_result), Some(bump)) = (ctx.accounts.spin_result.as_mut(), ctx.bumps.spin_result) {
+            spin_result.record(&settled, commitment, secret_value, salt, commitment.randomness_account, bump)?;
+            spin_result.oracle_value = switchboard_randomness;
+            spin_result.record_grid(&ctx.accounts.paytable.reels, &outcome.grid);
         }
-
-        msg!("Switchboard-ready slot machine completed! Random: {}", secure_random);
+        msg!("Switchboard slot machine completed! Random: {}", settled.random_value);
         Ok(())
     }
 
     // Phase 2: Player reveals the secret and spins the slot machine (fallback without Switchboard)
     pub fn reveal_and_spin(ctx: Context<RevealAndSpin>, secret_value: u64, salt: u64) -> Result<()> {
+        // The fallback path is refused for commitments placed in oracle-only mode
+        require!(!ctx.accounts.commitment.use_switchboard, SlotMachineError::SwitchboardRequired);
+
         msg!("Using fallback randomness. Consider upgrading to Switchboard on-demand for maximum security.");
         
         let commitment = &mut ctx.accounts.commitment;
         
-        // Verify the commitment hasn't been revealed already
-        require!(!commitment.revealed, SlotMachineError::AlreadyRevealed);
+        // A commitment bound to Switchboard randomness can only settle through consume_randomness
+        commitment.require_status(CommitmentStatus::Committed)?;
 
-        // Verify minimum delay has passed
-        let current_time = Clock::get()?.unix_timestamp;
-        let time_elapsed = current_time - commitment.timestamp;
-        require!(time_elapsed >= MIN_DELAY_SECONDS, SlotMachineError::InsufficientDelay);
+        // The entropy slot's hash only exists once a later slot has been produced
+        let clock = Clock::get()?;
+        let slots_elapsed = clock.slot.saturating_sub(commitment.commit_slot);
+        require!(clock.slot > commitment.entropy_slot, SlotMachineError::InsufficientDelay);
+        require!(
+            !commitment.is_expired(&ctx.accounts.game_config, clock.slot),
+            SlotMachineError::CommitmentExpired
+        );
 
         // Verify the revealed value matches the commitment
         let mut hasher = Sha256::new();
@@ -207,82 +1045,162 @@ pub mod slot_machine {
             SlotMachineError::InvalidReveal
         );
 
-        // Mark as revealed to prevent replay
-        commitment.revealed = true;
+        require!(commitment.bet_mint == ctx.accounts.game_config.bet_mint, SlotMachineError::WrongBetCurrency);
+        // Odds are fixed at commit time; the paytable is locked while commitments are open
+        require!(
+            commitment.paytable_version == ctx.accounts.paytable.version,
+            SlotMachineError::PaytableChanged
+        );
+
+        // Release the reserved payout; closing the commitment is what prevents a replay
+        release_liability(&mut ctx.accounts.game_config, commitment.max_payout);
+
+        // The entropy slot was fixed at commit time and its hash did not exist yet, so the
+        // secret was chosen blind, and picking when to reveal does not change which hash is used
+        let (hash_slot, slot_hash) = slot_hash_at(&ctx.accounts.slot_hashes, commitment.entropy_slot)?;
 
         // Generate secure randomness by combining multiple entropy sources
-        let clock = Clock::get()?;
         let mut main_hasher = Sha256::new();
         
         main_hasher.update(secret_value.to_le_bytes());
         main_hasher.update(salt.to_le_bytes());
-        main_hasher.update(clock.slot.to_le_bytes());
-        main_hasher.update(clock.unix_timestamp.to_le_bytes());
+        main_hasher.update(slot_hash);
+        main_hasher.update(hash_slot.to_le_bytes());
         main_hasher.update(ctx.accounts.player.key().as_ref());
         main_hasher.update(commitment.bet_amount.to_le_bytes());
-        main_hasher.update(commitment.timestamp.to_le_bytes());
+        main_hasher.update(commitment.commit_slot.to_le_bytes());
 
-        let final_hash = main_hasher.finalize();
-        let random_bytes = u64::from_le_bytes(
-            final_hash[0..8].try_into().unwrap()
-        );
-        let secure_random = (random_bytes % 100) + 1;
+        let final_hash: [u8; 32] = main_hasher.finalize().into();
+        let outcome = ctx.accounts.paytable.resolve(final_hash)?;
+        msg!("Fallback secure random value: {}", outcome.roll);
 
-        msg!("Fallback secure random value: {}", secure_random);
+        // Same payout logic, read from the on-chain paytable
+        let settled = settle_outcome(
+            SettlementAccounts {
+                game_config: &ctx.accounts.game_config,
+                game_stats: &mut ctx.accounts.game_stats,
+                paytable: &ctx.accounts.paytable,
+                treasury: &ctx.accounts.treasury,
+                player: &ctx.accounts.player,
+                jackpot_pool: &mut ctx.accounts.jackpot_pool,
+                player_state: &mut ctx.accounts.player_state,
+                player_state_bump: ctx.bumps.player_state,
+                bet_mint: &ctx.accounts.bet_mint,
+                token_vault: &ctx.accounts.token_vault,
+                player_token_account: &ctx.accounts.player_token_account,
+                token_program: &ctx.accounts.token_program,
+                system_program: &ctx.accounts.system_program,
+            },
+            SettledBet::of(commitment, SettlementSource::CommitReveal),
+            &outcome,
+        )?;
+        if let (Some(spin_result), Some(bump)) = (ctx.accounts.spin_result.as_mut(), ctx.bumps.spin_result) {
+            spin_result.record(&settled, commitment, secret_value, salt, Pubkey::default(), bump)?;
+            spin_result.hash_slot = hash_slot;
+            spin_result.slot_hash = slot_hash;
+            spin_result.record_grid(&ctx.accounts.paytable.reels, &outcome.grid);
+        }
+        msg!("Spin completed successfully! Random: {}, Slots elapsed: {}", settled.random_value, slots_elapsed);
+        Ok(())
+    }
 
-        // Same payout logic
-        let payout_tiers = [
-            (99.5, 25, "JACKPOT!"),
-            (98.0, 10, "Big Win!"),
-            (95.0, 6,  "Great!"),
-            (90.0, 3,  "Nice!"),
-            (80.0, 2,  "Win!"),
-            (65.0, 1,  "Break Even"),
-        ];
+    // Closes a commitment left unrevealed past the deadline. Anyone may call it; the rent always
+    // returns to the player and the bet is refunded or kept by the treasury per the expiry policy.
+    // A refund is only paid while the outcome could not have been computed yet; otherwise a
+    // player could leave losing spins open and take their bet back.
+    pub fn expire_commitment(ctx: Context<ExpireCommitment>) -> Result<()> {
+        let commitment = &ctx.accounts.commitment;
+        require!(commitment.bet_mint == ctx.accounts.game_config.bet_mint, SlotMachineError::WrongBetCurrency);
 
-        let (payout_multiplier, win_message) = payout_tiers
-            .iter()
-            .find(|(threshold, _, _)| secure_random > *threshold as u64)
-            .map(|(_, multiplier, message)| (*multiplier, *message))
-            .unwrap_or((0, "Try Again!"));
-            
-        let win = payout_multiplier > 0;
-
-        if win {
-            let payout = commitment.bet_amount * payout_multiplier;
-            let treasury_bump = [ctx.accounts.game_config.treasury_bump];
-            let treasury_seeds: &[&[u8]] = &[TREASURY_SEED, &treasury_bump];
-            let signer_seeds: &[&[&[u8]]] = &[treasury_seeds];
-            
-            let transfer_accounts = SystemTransfer {
-                from: ctx.accounts.treasury.to_account_info(),
-                to: ctx.accounts.player.to_account_info(),
-            };
-            let transfer_ctx = CpiContext::new_with_signer(
-                ctx.accounts.system_program.to_account_info(),
-                transfer_accounts,
-                signer_seeds,
-            );
-            system_program::transfer(transfer_ctx, payout)?;
+        let current_slot = Clock::get()?.slot;
+        require!(
+            commitment.is_expired(&ctx.accounts.game_config, current_slot),
+            SlotMachineError::CommitmentNotExpired
+        );
+
+        release_liability(&mut ctx.accounts.game_config, commitment.max_payout);
 
-            msg!("{} You won! Payout: {} lamports ({}x multiplier)", win_message, payout, payout_multiplier);
+        // Free spins never transferred a bet, so there is nothing to refund
+        let refund = if commitment.free_spin || commitment.is_outcome_known(current_slot) {
+            0
+        } else if ctx.accounts.game_config.expiry_policy == ExpiryPolicy::Refund {
+            commitment.bet_amount
         } else {
-            msg!("{} Better luck next time!", win_message);
+            0
+        };
+        if refund > 0 {
+            let token_accounts = treasury_token_accounts(
+                &ctx.accounts.game_config,
+                &ctx.accounts.bet_mint,
+                &ctx.accounts.token_vault,
+                &ctx.accounts.player_token_account,
+                &ctx.accounts.token_program,
+            )?;
+            pay_from_treasury(
+                &ctx.accounts.game_config,
+                ctx.accounts.treasury.to_account_info(),
+                ctx.accounts.player.to_account_info(),
+                token_accounts,
+                ctx.accounts.system_program.to_account_info(),
+                refund,
+            )?;
         }
 
-        msg!("Spin completed successfully! Random: {}, Time elapsed: {}s", secure_random, time_elapsed);
+        let outstanding_liability = ctx.accounts.game_config.outstanding_liability;
+        ctx.accounts.game_stats.record_refund(refund, outstanding_liability)?;
+        emit!(CommitmentRefunded {
+            player: commitment.player,
+            game_config: commitment.game_config,
+            commitment: commitment.key(),
+            bet_mint: commitment.bet_mint,
+            refund,
+        });
+        msg!("Commitment expired. Refunded: {}", refund);
+        Ok(())
+    }
+
+    // Closes a settled spin's record once the player no longer needs it for disputes
+    pub fn close_spin_result(ctx: Context<CloseSpinResult>) -> Result<()> {
+        let spin_result = &ctx.accounts.spin_result;
+        emit!(SpinResultClosed {
+            player: spin_result.player,
+            game_config: spin_result.game_config,
+            spin_result: spin_result.key(),
+            nonce: spin_result.nonce,
+        });
+        msg!("Spin result {} closed", spin_result.nonce);
         Ok(())
     }
 
     // Legacy spin function - kept for backward compatibility but should be deprecated
     pub fn spin(ctx: Context<Spin>, bet_amount: u64) -> Result<()> {
         msg!("Warning: Using deprecated spin function. Please use Switchboard-ready commit-reveal scheme instead.");
+        require!(!ctx.accounts.game_config.paused, SlotMachineError::MachinePaused);
+        require!(!ctx.accounts.game_config.is_token_game(), SlotMachineError::WrongBetCurrency);
         
         // Validate bet amount is within allowed range
-        require!(bet_amount >= MIN_BET_AMOUNT, SlotMachineError::BetTooLow);
-        require!(bet_amount <= MAX_BET_AMOUNT, SlotMachineError::BetTooHigh);
+        require!(bet_amount >= ctx.accounts.game_config.min_bet_amount, SlotMachineError::BetTooLow);
+        require!(bet_amount <= ctx.accounts.game_config.max_bet_amount, SlotMachineError::BetTooHigh);
+        require_jackpot_pool(&ctx.accounts.game_config, &ctx.accounts.jackpot_pool)?;
+
+        // Feed the progressive jackpot with its share of the bet
+        let contribution = jackpot_contribution(&ctx.accounts.jackpot_pool, bet_amount)?;
+        if let Some(jackpot_pool) = ctx.accounts.jackpot_pool.as_mut() {
+            if contribution > 0 {
+                let cpi_accounts = SystemTransfer {
+                    from: ctx.accounts.player.to_account_info(),
+                    to: jackpot_pool.to_account_info(),
+                };
+                let cpi_context = CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    cpi_accounts,
+                );
+                system_program::transfer(cpi_context, contribution)?;
+                jackpot_pool.balance = jackpot_pool.balance.checked_add(contribution).ok_or(SlotMachineError::MathOverflow)?;
+            }
+        }
 
-        // Transfer the bet from the player to the treasury
+        // Transfer the rest of the bet from the player to the treasury
         let cpi_accounts = SystemTransfer {
             from: ctx.accounts.player.to_account_info(),
             to: ctx.accounts.treasury.to_account_info(),
@@ -291,7 +1209,14 @@ pub mod slot_machine {
             ctx.accounts.system_program.to_account_info(),
             cpi_accounts,
         );
-        system_program::transfer(cpi_context, bet_amount)?;
+        system_program::transfer(cpi_context, bet_amount - contribution)?;
+
+        // The treasury must cover the worst-case payout on top of every open commitment
+        let max_payout = bet_amount
+            .checked_mul(ctx.accounts.paytable.max_multiplier()?)
+            .ok_or(SlotMachineError::MathOverflow)?;
+        let available = treasury_available(&ctx.accounts.game_config, &ctx.accounts.treasury.to_account_info(), &None)?;
+        check_solvency(&ctx.accounts.game_config, available, max_payout)?;
 
         // Simple randomness (insecure - for compatibility only)
         let clock = Clock::get()?;
@@ -301,73 +1226,70 @@ pub mod slot_machine {
         hasher.update(clock.unix_timestamp.to_le_bytes());
         hasher.update(bet_amount.to_le_bytes());
 
-        let hash_result = hasher.finalize();
-        let random_seed = u64::from_le_bytes(hash_result[0..8].try_into().unwrap());
-        let secure_random = (random_seed % 100) + 1;
+        let hash_result: [u8; 32] = hasher.finalize().into();
+        let outcome = ctx.accounts.paytable.resolve(hash_result)?;
+        msg!("Insecure random value: {}", outcome.roll);
 
-        msg!("Insecure random value: {}", secure_random);
+        let player_state = &mut ctx.accounts.player_state;
+        player_state.initialize_if_needed(ctx.accounts.player.key(), ctx.accounts.game_config.key(), ctx.bumps.player_state);
+        player_state.record_wager(bet_amount, clock.unix_timestamp)?;
 
         // Same payout logic as reveal_and_spin
-        let payout_tiers = [
-            (99.5, 25, "JACKPOT!"),
-            (98.0, 10, "Big Win!"),
-            (95.0, 6,  "Great!"),
-            (90.0, 3,  "Nice!"),
-            (80.0, 2,  "Win!"),
-            (65.0, 1,  "Break Even"),
-        ];
-
-        let (payout_multiplier, win_message) = payout_tiers
-            .iter()
-            .find(|(threshold, _, _)| secure_random > *threshold as u64)
-            .map(|(_, multiplier, message)| (*multiplier, *message))
-            .unwrap_or((0, "Try Again!"));
-            
-        let win = payout_multiplier > 0;
-
-        if win {
-            let payout = bet_amount * payout_multiplier;
-            let treasury_bump = [ctx.accounts.game_config.treasury_bump];
-            let treasury_seeds: &[&[u8]] = &[TREASURY_SEED, &treasury_bump];
-            let signer_seeds: &[&[&[u8]]] = &[treasury_seeds];
-            
-            let transfer_accounts = SystemTransfer {
-                from: ctx.accounts.treasury.to_account_info(),
-                to: ctx.accounts.player.to_account_info(),
-            };
-            let transfer_ctx = CpiContext::new_with_signer(
-                ctx.accounts.system_program.to_account_info(),
-                transfer_accounts,
-                signer_seeds,
-            );
-            system_program::transfer(transfer_ctx, payout)?;
-
-            msg!("{} You won! Payout: {} lamports ({}x multiplier)", win_message, payout, payout_multiplier);
-        } else {
-            msg!("{} Better luck next time!", win_message);
-        }
-
+        settle_outcome(
+            SettlementAccounts {
+                game_config: &ctx.accounts.game_config,
+                game_stats: &mut ctx.accounts.game_stats,
+                paytable: &ctx.accounts.paytable,
+                treasury: &ctx.accounts.treasury,
+                player: &ctx.accounts.player,
+                jackpot_pool: &mut ctx.accounts.jackpot_pool,
+                player_state: &mut ctx.accounts.player_state,
+                player_state_bump: ctx.bumps.player_state,
+                bet_mint: &None,
+                token_vault: &None,
+                player_token_account: &None,
+                token_program: &None,
+                system_program: &ctx.accounts.system_program,
+            },
+            SettledBet {
+                commitment: Pubkey::default(),
+                bet_mint: Pubkey::default(),
+                bet_amount,
+                free_spin: false,
+                source: SettlementSource::LegacySpin,
+            },
+            &outcome,
+        )?;
         Ok(())
     }
 }
 
 // Define the account structures for each instruction
 #[derive(Accounts)]
+#[instruction(machine_id: u64)]
 pub struct Initialize<'info> {
     #[account(
         init,
         payer = authority,
-        space = 8 + 32 + 1, // 8 for discriminator, 32 for authority pubkey, 1 for bump
-        seeds = [b"game_config".as_ref(), authority.key().as_ref()],
+        space = 8 + 32 + 8 + 1 + 32 + 32 + 8 + 8 + 1 + 32 + 8 + 8 + 8 + 32 + 1 + 32 + 32 + 8 + 8 + 8 + 32 + 8 + 8, // 8 for discriminator, 32 for authority pubkey, 8 for machine id, 1 for bump, 32 for paytable pubkey, 32 for jackpot pool pubkey, 8 for outstanding liability, 8 for reveal deadline, 1 for expiry policy, 32 for bet mint, 8 + 8 for bet limits, 8 for reveal delay, 32 for guardian, 1 for paused flag, 32 for pending authority, 32 for randomness client, 8 for withdrawal timelock, 8 for pending withdrawal, 8 for unlock time, 32 for LP share mint, 8 for pending timelock, 8 for timelock change time
+        seeds = [b"game_config".as_ref(), authority.key().as_ref(), &machine_id.to_le_bytes()],
         bump
     )]
     pub game_config: Account<'info, GameConfig>,
     #[account(
         mut,
-        seeds = [TREASURY_SEED],
+        seeds = [TREASURY_SEED, game_config.key().as_ref()],
         bump
     )]
     pub treasury: SystemAccount<'info>,
+    #[account(
+        init,
+        payer = authority,
+        space = GAME_STATS_SPACE,
+        seeds = [GAME_STATS_SEED, game_config.key().as_ref()],
+        bump
+    )]
+    pub game_stats: Account<'info, GameStats>,
     #[account(mut)]
     pub authority: Signer<'info>,
     pub system_program: Program<'info, System>,
@@ -380,15 +1302,148 @@ pub struct Commit<'info> {
     pub game_config: Account<'info, GameConfig>,
     #[account(
         mut,
-        seeds = [TREASURY_SEED],
+        seeds = [GAME_STATS_SEED, game_config.key().as_ref()],
+        bump = game_stats.bump
+    )]
+    pub game_stats: Account<'info, GameStats>,
+    #[account(
+        address = game_config.paytable @ SlotMachineError::InvalidPaytable
+    )]
+    pub paytable: Account<'info, Paytable>,
+    #[account(
+        address = game_config.randomness_client @ SlotMachineError::InvalidRandomnessClient
+    )]
+    pub randomness_client: Account<'info, RandomnessClient>,
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED, game_config.key().as_ref()],
+        bump = game_config.treasury_bump
+    )]
+    pub treasury: SystemAccount<'info>,
+    #[account(
+        init,
+        payer = player,
+        space = COMMITMENT_SPACE,
+        seeds = [COMMITMENT_SEED, game_config.key().as_ref(), player.key().as_ref(), &nonce.to_le_bytes()],
+        bump
+    )]
+    pub commitment: Account<'info, Commitment>,
+    #[account(
+        mut,
+        address = game_config.jackpot_pool @ SlotMachineError::InvalidJackpotPool
+    )]
+    pub jackpot_pool: Option<Account<'info, JackpotPool>>,
+    #[account(
+        init_if_needed,
+        payer = player,
+        space = PLAYER_STATE_SPACE,
+        seeds = [PLAYER_STATE_SEED, game_config.key().as_ref(), player.key().as_ref()],
+        bump
+    )]
+    pub player_state: Account<'info, PlayerState>,
+    #[account(mut)]
+    pub player: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(commitment_hash: [u8; 32], bet_amount: u64, nonce: u64)]
+pub struct CommitToken<'info> {
+    #[account(mut)]
+    pub game_config: Account<'info, GameConfig>,
+    #[account(
+        mut,
+        seeds = [GAME_STATS_SEED, game_config.key().as_ref()],
+        bump = game_stats.bump
+    )]
+    pub game_stats: Account<'info, GameStats>,
+    #[account(
+        address = game_config.paytable @ SlotMachineError::InvalidPaytable
+    )]
+    pub paytable: Account<'info, Paytable>,
+    #[account(
+        address = game_config.randomness_client @ SlotMachineError::InvalidRandomnessClient
+    )]
+    pub randomness_client: Account<'info, RandomnessClient>,
+    #[account(
+        address = game_config.bet_mint @ SlotMachineError::InvalidTokenAccount
+    )]
+    pub bet_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        seeds = [TOKEN_VAULT_SEED, game_config.key().as_ref()],
+        bump
+    )]
+    pub token_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = player_token_account.mint == game_config.bet_mint @ SlotMachineError::InvalidTokenAccount,
+        constraint = player_token_account.owner == player.key() @ SlotMachineError::InvalidTokenAccount
+    )]
+    pub player_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        init,
+        payer = player,
+        space = COMMITMENT_SPACE,
+        seeds = [COMMITMENT_SEED, game_config.key().as_ref(), player.key().as_ref(), &nonce.to_le_bytes()],
+        bump
+    )]
+    pub commitment: Account<'info, Commitment>,
+    #[account(
+        init_if_needed,
+        payer = player,
+        space = PLAYER_STATE_SPACE,
+        seeds = [PLAYER_STATE_SEED, game_config.key().as_ref(), player.key().as_ref()],
+        bump
+    )]
+    pub player_state: Account<'info, PlayerState>,
+    #[account(mut)]
+    pub player: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(commitment_hash: [u8; 32], nonce: u64)]
+pub struct CommitFreeSpin<'info> {
+    #[account(mut)]
+    pub game_config: Account<'info, GameConfig>,
+    #[account(
+        mut,
+        seeds = [GAME_STATS_SEED, game_config.key().as_ref()],
+        bump = game_stats.bump
+    )]
+    pub game_stats: Account<'info, GameStats>,
+    #[account(
+        address = game_config.paytable @ SlotMachineError::InvalidPaytable
+    )]
+    pub paytable: Account<'info, Paytable>,
+    #[account(
+        address = game_config.randomness_client @ SlotMachineError::InvalidRandomnessClient
+    )]
+    pub randomness_client: Account<'info, RandomnessClient>,
+    #[account(
+        seeds = [TREASURY_SEED, game_config.key().as_ref()],
         bump = game_config.treasury_bump
     )]
     pub treasury: SystemAccount<'info>,
+    #[account(
+        seeds = [TOKEN_VAULT_SEED, game_config.key().as_ref()],
+        bump
+    )]
+    pub token_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds = [PLAYER_STATE_SEED, game_config.key().as_ref(), player.key().as_ref()],
+        bump = player_state.bump,
+        has_one = player @ SlotMachineError::InvalidPlayer
+    )]
+    pub player_state: Account<'info, PlayerState>,
     #[account(
         init,
         payer = player,
-        space = 8 + 32 + 32 + 8 + 8 + 1 + 1 + 8 + 1, // discriminator + player + hash + bet_amount + timestamp + revealed + bump + nonce + randomness_requested
-        seeds = [COMMITMENT_SEED, player.key().as_ref(), &nonce.to_le_bytes()],
+        space = COMMITMENT_SPACE,
+        seeds = [COMMITMENT_SEED, game_config.key().as_ref(), player.key().as_ref(), &nonce.to_le_bytes()],
         bump
     )]
     pub commitment: Account<'info, Commitment>,
@@ -403,17 +1458,71 @@ pub struct RevealAndSpin<'info> {
     pub game_config: Account<'info, GameConfig>,
     #[account(
         mut,
-        seeds = [TREASURY_SEED],
+        seeds = [GAME_STATS_SEED, game_config.key().as_ref()],
+        bump = game_stats.bump
+    )]
+    pub game_stats: Account<'info, GameStats>,
+    #[account(
+        address = game_config.paytable @ SlotMachineError::InvalidPaytable
+    )]
+    pub paytable: Account<'info, Paytable>,
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED, game_config.key().as_ref()],
         bump = game_config.treasury_bump
     )]
     pub treasury: SystemAccount<'info>,
     #[account(
         mut,
-        seeds = [COMMITMENT_SEED, player.key().as_ref(), &commitment.nonce.to_le_bytes()],
+        close = player,
+        seeds = [COMMITMENT_SEED, game_config.key().as_ref(), player.key().as_ref(), &commitment.nonce.to_le_bytes()],
         bump = commitment.bump,
-        constraint = commitment.player == *player.key @ SlotMachineError::InvalidPlayer
+        constraint = commitment.player == *player.key @ SlotMachineError::InvalidPlayer,
+        has_one = game_config @ SlotMachineError::InvalidGameConfig
     )]
     pub commitment: Account<'info, Commitment>,
+    #[account(
+        mut,
+        address = game_config.jackpot_pool @ SlotMachineError::InvalidJackpotPool
+    )]
+    pub jackpot_pool: Option<Account<'info, JackpotPool>>,
+    #[account(
+        address = game_config.bet_mint @ SlotMachineError::InvalidTokenAccount
+    )]
+    pub bet_mint: Option<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        seeds = [TOKEN_VAULT_SEED, game_config.key().as_ref()],
+        bump
+    )]
+    pub token_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = player_token_account.mint == game_config.bet_mint @ SlotMachineError::InvalidTokenAccount,
+        constraint = player_token_account.owner == player.key() @ SlotMachineError::InvalidTokenAccount
+    )]
+    pub player_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    #[account(
+        init_if_needed,
+        payer = player,
+        space = PLAYER_STATE_SPACE,
+        seeds = [PLAYER_STATE_SEED, game_config.key().as_ref(), player.key().as_ref()],
+        bump
+    )]
+    pub player_state: Account<'info, PlayerState>,
+    // Optional permanent record of the outcome; close_spin_result returns its rent
+    #[account(
+        init,
+        payer = player,
+        space = SPIN_RESULT_SPACE,
+        seeds = [SPIN_RESULT_SEED, game_config.key().as_ref(), player.key().as_ref(), &commitment.nonce.to_le_bytes()],
+        bump
+    )]
+    pub spin_result: Option<Account<'info, SpinResult>>,
+    /// CHECK: address-checked SlotHashes sysvar, read by slot_hash_at
+    #[account(address = anchor_lang::solana_program::sysvar::slot_hashes::ID)]
+    pub slot_hashes: UncheckedAccount<'info>,
     #[account(mut)]
     pub player: Signer<'info>,
     pub system_program: Program<'info, System>,
@@ -425,15 +1534,128 @@ pub struct Spin<'info> {
     pub game_config: Account<'info, GameConfig>,
     #[account(
         mut,
-        seeds = [TREASURY_SEED],
+        seeds = [GAME_STATS_SEED, game_config.key().as_ref()],
+        bump = game_stats.bump
+    )]
+    pub game_stats: Account<'info, GameStats>,
+    #[account(
+        address = game_config.paytable @ SlotMachineError::InvalidPaytable
+    )]
+    pub paytable: Account<'info, Paytable>,
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED, game_config.key().as_ref()],
         bump = game_config.treasury_bump
     )]
     pub treasury: SystemAccount<'info>,
+    #[account(
+        mut,
+        address = game_config.jackpot_pool @ SlotMachineError::InvalidJackpotPool
+    )]
+    pub jackpot_pool: Option<Account<'info, JackpotPool>>,
+    #[account(
+        init_if_needed,
+        payer = player,
+        space = PLAYER_STATE_SPACE,
+        seeds = [PLAYER_STATE_SEED, game_config.key().as_ref(), player.key().as_ref()],
+        bump
+    )]
+    pub player_state: Account<'info, PlayerState>,
     #[account(mut)]
     pub player: Signer<'info>,
     pub system_program: Program<'info, System>,
 }
 
+#[derive(Accounts)]
+pub struct CreatePaytable<'info> {
+    #[account(
+        mut,
+        has_one = authority @ SlotMachineError::Unauthorized
+    )]
+    pub game_config: Account<'info, GameConfig>,
+
+    #[account(
+        init,
+        payer = authority,
+        // discriminator + game_config + odds_denominator + max_rtp_bps + version + bump
+        // + tiers (threshold + multiplier + label + jackpot) + rows + reel strips + paylines + symbol pays (symbol + count + multiplier + jackpot)
+        // + wild + scatter + scatter pays (count + multiplier + free_spins)
+        space = 8 + 32 + 8 + 2 + 4 + 1
+            + 4 + MAX_PAYTABLE_TIERS * (8 + 8 + 4 + MAX_TIER_LABEL_LEN + 1)
+            + 1
+            + 4 + MAX_REELS * (4 + MAX_STRIP_LEN)
+            + 4 + MAX_PAYLINES * (4 + MAX_REELS)
+            + 4 + MAX_SYMBOL_PAYS * (1 + 1 + 8 + 1)
+            + 2 + 2
+            + 4 + MAX_SCATTER_PAYS * (1 + 8 + 1),
+        seeds = [PAYTABLE_SEED, game_config.key().as_ref()],
+        bump
+    )]
+    pub paytable: Account<'info, Paytable>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdatePaytable<'info> {
+    #[account(
+        has_one = authority @ SlotMachineError::Unauthorized
+    )]
+    pub game_config: Account<'info, GameConfig>,
+
+    #[account(
+        mut,
+        seeds = [PAYTABLE_SEED, game_config.key().as_ref()],
+        bump = paytable.bump,
+        address = game_config.paytable @ SlotMachineError::InvalidPaytable
+    )]
+    pub paytable: Account<'info, Paytable>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CreateJackpotPool<'info> {
+    #[account(
+        mut,
+        has_one = authority @ SlotMachineError::Unauthorized
+    )]
+    pub game_config: Account<'info, GameConfig>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + 32 + 2 + 8 + 8 + 8 + 32 + 8 + 1, // discriminator + game_config + contribution_bps + reseed_amount + balance + hits + last_winner + last_payout + bump
+        seeds = [JACKPOT_SEED, game_config.key().as_ref()],
+        bump
+    )]
+    pub jackpot_pool: Account<'info, JackpotPool>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateJackpotPool<'info> {
+    #[account(
+        has_one = authority @ SlotMachineError::Unauthorized
+    )]
+    pub game_config: Account<'info, GameConfig>,
+
+    #[account(
+        mut,
+        seeds = [JACKPOT_SEED, game_config.key().as_ref()],
+        bump = jackpot_pool.bump,
+        address = game_config.jackpot_pool @ SlotMachineError::InvalidJackpotPool
+    )]
+    pub jackpot_pool: Account<'info, JackpotPool>,
+
+    pub authority: Signer<'info>,
+}
+
 #[derive(Accounts)]
 pub struct InitRandomnessClient<'info> {
     #[account(mut)]
@@ -442,7 +1664,7 @@ pub struct InitRandomnessClient<'info> {
     #[account(
         init,
         payer = authority,
-        space = 8 + 32 + 1 + 1, // discriminator + authority + bump + use_switchboard
+        space = 8 + 32 + 1 + 1 + 32, // discriminator + authority + bump + use_switchboard + pending_authority
         seeds = [RANDOMNESS_SEED, authority.key().as_ref()],
         bump
     )]
@@ -452,71 +1674,978 @@ pub struct InitRandomnessClient<'info> {
 }
 
 #[derive(Accounts)]
-pub struct RequestRandomness<'info> {
+pub struct UpdateConfig<'info> {
+    #[account(
+        mut,
+        has_one = authority @ SlotMachineError::Unauthorized
+    )]
+    pub game_config: Account<'info, GameConfig>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseSpinResult<'info> {
+    #[account(
+        mut,
+        close = player,
+        has_one = player @ SlotMachineError::InvalidPlayer
+    )]
+    pub spin_result: Account<'info, SpinResult>,
     #[account(mut)]
     pub player: Signer<'info>,
-    
-    pub authority: Signer<'info>,
-    
+}
+
+#[derive(Accounts)]
+pub struct DepositTreasury<'info> {
+    pub game_config: Account<'info, GameConfig>,
     #[account(
         mut,
-        seeds = [COMMITMENT_SEED, player.key().as_ref(), &commitment.nonce.to_le_bytes()],
-        bump = commitment.bump,
-        constraint = commitment.player == *player.key @ SlotMachineError::InvalidPlayer
+        seeds = [TREASURY_SEED, game_config.key().as_ref()],
+        bump = game_config.treasury_bump
     )]
-    pub commitment: Account<'info, Commitment>,
-    
+    pub treasury: SystemAccount<'info>,
     #[account(
-        seeds = [RANDOMNESS_SEED, authority.key().as_ref()],
-        bump = randomness_client.bump
+        address = game_config.bet_mint @ SlotMachineError::InvalidTokenAccount
     )]
-    pub randomness_client: Account<'info, RandomnessClient>,
-    
+    pub bet_mint: Option<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        seeds = [TOKEN_VAULT_SEED, game_config.key().as_ref()],
+        bump
+    )]
+    pub token_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = depositor_token_account.mint == game_config.bet_mint @ SlotMachineError::InvalidTokenAccount,
+        constraint = depositor_token_account.owner == depositor.key() @ SlotMachineError::InvalidTokenAccount
+    )]
+    pub depositor_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    #[account(mut)]
+    pub depositor: Signer<'info>,
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct ConsumeRandomness<'info> {
-    #[account(mut)]
-    pub player: Signer<'info>,
-    
+pub struct WithdrawTreasury<'info> {
     #[account(
         mut,
-        seeds = [COMMITMENT_SEED, player.key().as_ref(), &commitment.nonce.to_le_bytes()],
-        bump = commitment.bump,
-        constraint = commitment.player == *player.key @ SlotMachineError::InvalidPlayer
+        has_one = authority @ SlotMachineError::Unauthorized
     )]
-    pub commitment: Account<'info, Commitment>,
-    
+    pub game_config: Account<'info, GameConfig>,
     #[account(
         mut,
-        seeds = [TREASURY_SEED],
+        seeds = [TREASURY_SEED, game_config.key().as_ref()],
         bump = game_config.treasury_bump
     )]
     pub treasury: SystemAccount<'info>,
-    
-    pub game_config: Account<'info, GameConfig>,
-    
+    #[account(
+        address = game_config.bet_mint @ SlotMachineError::InvalidTokenAccount
+    )]
+    pub bet_mint: Option<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        seeds = [TOKEN_VAULT_SEED, game_config.key().as_ref()],
+        bump
+    )]
+    pub token_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = authority_token_account.mint == game_config.bet_mint @ SlotMachineError::InvalidTokenAccount,
+        constraint = authority_token_account.owner == authority.key() @ SlotMachineError::InvalidTokenAccount
+    )]
+    pub authority_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
     pub system_program: Program<'info, System>,
 }
 
-// Define the GameConfig account data structure
-#[account]
-pub struct GameConfig {
-    pub authority: Pubkey,
-    pub treasury_bump: u8,
-}
-
-#[account]
-pub struct Commitment {
-    pub player: Pubkey,           // 32 bytes
-    pub commitment_hash: [u8; 32], // 32 bytes
-    pub bet_amount: u64,          // 8 bytes
+#[derive(Accounts)]
+pub struct CreateLiquidityPool<'info> {
+    #[account(
+        mut,
+        has_one = authority @ SlotMachineError::Unauthorized
+    )]
+    pub game_config: Account<'info, GameConfig>,
+    #[account(
+        seeds = [TREASURY_SEED, game_config.key().as_ref()],
+        bump = game_config.treasury_bump
+    )]
+    pub treasury: SystemAccount<'info>,
+    #[account(
+        init,
+        payer = authority,
+        seeds = [LP_MINT_SEED, game_config.key().as_ref()],
+        bump,
+        mint::decimals = LP_SHARE_DECIMALS,
+        mint::authority = treasury,
+        mint::token_program = lp_token_program
+    )]
+    pub lp_mint: InterfaceAccount<'info, Mint>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub lp_token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DepositLiquidity<'info> {
+    pub game_config: Account<'info, GameConfig>,
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED, game_config.key().as_ref()],
+        bump = game_config.treasury_bump
+    )]
+    pub treasury: SystemAccount<'info>,
+    #[account(
+        mut,
+        address = game_config.lp_mint @ SlotMachineError::InvalidLiquidityPool
+    )]
+    pub lp_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        constraint = depositor_lp_account.mint == lp_mint.key() @ SlotMachineError::InvalidTokenAccount,
+        constraint = depositor_lp_account.owner == depositor.key() @ SlotMachineError::InvalidTokenAccount
+    )]
+    pub depositor_lp_account: InterfaceAccount<'info, TokenAccount>,
+    pub lp_token_program: Interface<'info, TokenInterface>,
+    #[account(
+        address = game_config.bet_mint @ SlotMachineError::InvalidTokenAccount
+    )]
+    pub bet_mint: Option<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        seeds = [TOKEN_VAULT_SEED, game_config.key().as_ref()],
+        bump
+    )]
+    pub token_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = depositor_token_account.mint == game_config.bet_mint @ SlotMachineError::InvalidTokenAccount,
+        constraint = depositor_token_account.owner == depositor.key() @ SlotMachineError::InvalidTokenAccount
+    )]
+    pub depositor_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    #[account(mut)]
+    pub depositor: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawLiquidity<'info> {
+    pub game_config: Account<'info, GameConfig>,
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED, game_config.key().as_ref()],
+        bump = game_config.treasury_bump
+    )]
+    pub treasury: SystemAccount<'info>,
+    #[account(
+        mut,
+        address = game_config.lp_mint @ SlotMachineError::InvalidLiquidityPool
+    )]
+    pub lp_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        constraint = provider_lp_account.mint == lp_mint.key() @ SlotMachineError::InvalidTokenAccount,
+        constraint = provider_lp_account.owner == provider.key() @ SlotMachineError::InvalidTokenAccount
+    )]
+    pub provider_lp_account: InterfaceAccount<'info, TokenAccount>,
+    pub lp_token_program: Interface<'info, TokenInterface>,
+    #[account(
+        address = game_config.bet_mint @ SlotMachineError::InvalidTokenAccount
+    )]
+    pub bet_mint: Option<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        seeds = [TOKEN_VAULT_SEED, game_config.key().as_ref()],
+        bump
+    )]
+    pub token_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = provider_token_account.mint == game_config.bet_mint @ SlotMachineError::InvalidTokenAccount,
+        constraint = provider_token_account.owner == provider.key() @ SlotMachineError::InvalidTokenAccount
+    )]
+    pub provider_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    #[account(mut)]
+    pub provider: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptAuthority<'info> {
+    #[account(
+        mut,
+        constraint = game_config.pending_authority == new_authority.key() @ SlotMachineError::Unauthorized
+    )]
+    pub game_config: Account<'info, GameConfig>,
+
+    pub new_authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetPaused<'info> {
+    #[account(
+        mut,
+        constraint = game_config.can_pause(&signer.key()) @ SlotMachineError::Unauthorized
+    )]
+    pub game_config: Account<'info, GameConfig>,
+
+    pub signer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ExpireCommitment<'info> {
+    #[account(mut)]
+    pub game_config: Account<'info, GameConfig>,
+    #[account(
+        mut,
+        seeds = [GAME_STATS_SEED, game_config.key().as_ref()],
+        bump = game_stats.bump
+    )]
+    pub game_stats: Account<'info, GameStats>,
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED, game_config.key().as_ref()],
+        bump = game_config.treasury_bump
+    )]
+    pub treasury: SystemAccount<'info>,
+    #[account(
+        mut,
+        close = player,
+        seeds = [COMMITMENT_SEED, game_config.key().as_ref(), player.key().as_ref(), &commitment.nonce.to_le_bytes()],
+        bump = commitment.bump,
+        constraint = commitment.player == *player.key @ SlotMachineError::InvalidPlayer,
+        has_one = game_config @ SlotMachineError::InvalidGameConfig
+    )]
+    pub commitment: Account<'info, Commitment>,
+    #[account(
+        address = game_config.bet_mint @ SlotMachineError::InvalidTokenAccount
+    )]
+    pub bet_mint: Option<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        seeds = [TOKEN_VAULT_SEED, game_config.key().as_ref()],
+        bump
+    )]
+    pub token_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = player_token_account.mint == game_config.bet_mint @ SlotMachineError::InvalidTokenAccount,
+        constraint = player_token_account.owner == player.key() @ SlotMachineError::InvalidTokenAccount
+    )]
+    pub player_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    // Receives the refund and the commitment rent; does not need to sign
+    #[account(mut)]
+    pub player: SystemAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct EnableTokenBetting<'info> {
+    #[account(
+        mut,
+        has_one = authority @ SlotMachineError::Unauthorized
+    )]
+    pub game_config: Account<'info, GameConfig>,
+
+    pub bet_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        seeds = [TREASURY_SEED, game_config.key().as_ref()],
+        bump = game_config.treasury_bump
+    )]
+    pub treasury: SystemAccount<'info>,
+
+    // Bets and payouts of the game, held in the treasury PDA's name
+    #[account(
+        init,
+        payer = authority,
+        seeds = [TOKEN_VAULT_SEED, game_config.key().as_ref()],
+        bump,
+        token::mint = bet_mint,
+        token::authority = treasury,
+        token::token_program = token_program
+    )]
+    pub token_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateRandomnessClient<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = authority @ SlotMachineError::Unauthorized
+    )]
+    pub randomness_client: Account<'info, RandomnessClient>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptRandomnessAuthority<'info> {
+    #[account(
+        mut,
+        constraint = randomness_client.pending_authority == new_authority.key() @ SlotMachineError::Unauthorized
+    )]
+    pub randomness_client: Account<'info, RandomnessClient>,
+
+    pub new_authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RequestRandomness<'info> {
+    // The player alone binds the randomness, so the operator cannot stall an oracle bet until it expires
+    #[account(mut)]
+    pub player: Signer<'info>,
+    
+    #[account(
+        mut,
+        seeds = [COMMITMENT_SEED, game_config.key().as_ref(), player.key().as_ref(), &commitment.nonce.to_le_bytes()],
+        bump = commitment.bump,
+        constraint = commitment.player == *player.key @ SlotMachineError::InvalidPlayer,
+        has_one = game_config @ SlotMachineError::InvalidGameConfig
+    )]
+    pub commitment: Account<'info, Commitment>,
+
+    pub game_config: Account<'info, GameConfig>,
+
+    /// CHECK: Parsed as Switchboard `RandomnessAccountData`; owner is checked against the on-demand program
+    #[account(
+        constraint = is_switchboard_program(randomness_account_data.owner) @ SlotMachineError::InvalidRandomnessAccount
+    )]
+    pub randomness_account_data: UncheckedAccount<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ConsumeRandomness<'info> {
+    #[account(mut)]
+    pub player: Signer<'info>,
+    
+    #[account(
+        mut,
+        close = player,
+        seeds = [COMMITMENT_SEED, game_config.key().as_ref(), player.key().as_ref(), &commitment.nonce.to_le_bytes()],
+        bump = commitment.bump,
+        constraint = commitment.player == *player.key @ SlotMachineError::InvalidPlayer,
+        has_one = game_config @ SlotMachineError::InvalidGameConfig
+    )]
+    pub commitment: Account<'info, Commitment>,
+    
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED, game_config.key().as_ref()],
+        bump = game_config.treasury_bump
+    )]
+    pub treasury: SystemAccount<'info>,
+    
+    #[account(mut)]
+    pub game_config: Account<'info, GameConfig>,
+    #[account(
+        mut,
+        seeds = [GAME_STATS_SEED, game_config.key().as_ref()],
+        bump = game_stats.bump
+    )]
+    pub game_stats: Account<'info, GameStats>,
+
+    #[account(
+        address = game_config.paytable @ SlotMachineError::InvalidPaytable
+    )]
+    pub paytable: Account<'info, Paytable>,
+
+    #[account(
+        mut,
+        address = game_config.jackpot_pool @ SlotMachineError::InvalidJackpotPool
+    )]
+    pub jackpot_pool: Option<Account<'info, JackpotPool>>,
+    #[account(
+        address = game_config.bet_mint @ SlotMachineError::InvalidTokenAccount
+    )]
+    pub bet_mint: Option<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        seeds = [TOKEN_VAULT_SEED, game_config.key().as_ref()],
+        bump
+    )]
+    pub token_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = player_token_account.mint == game_config.bet_mint @ SlotMachineError::InvalidTokenAccount,
+        constraint = player_token_account.owner == player.key() @ SlotMachineError::InvalidTokenAccount
+    )]
+    pub player_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+
+    #[account(
+        init_if_needed,
+        payer = player,
+        space = PLAYER_STATE_SPACE,
+        seeds = [PLAYER_STATE_SEED, game_config.key().as_ref(), player.key().as_ref()],
+        bump
+    )]
+    pub player_state: Account<'info, PlayerState>,
+
+    // Optional permanent record of the outcome; close_spin_result returns its rent
+    #[account(
+        init,
+        payer = player,
+        space = SPIN_RESULT_SPACE,
+        seeds = [SPIN_RESULT_SEED, game_config.key().as_ref(), player.key().as_ref(), &commitment.nonce.to_le_bytes()],
+        bump
+    )]
+    pub spin_result: Option<Account<'info, SpinResult>>,
+
+    /// CHECK: Must be the randomness account bound to the commitment in `request_randomness`
+    #[account(
+        constraint = randomness_account_data.key() == commitment.randomness_account @ SlotMachineError::InvalidRandomnessAccount
+    )]
+    pub randomness_account_data: UncheckedAccount<'info>,
+    
+    pub system_program: Program<'info, System>,
+}
+
+// Define the GameConfig account data structure
+#[account]
+pub struct GameConfig {
+    pub authority: Pubkey,
+    pub machine_id: u64,          // Distinguishes the machines of one authority
+    pub treasury_bump: u8,
+    pub paytable: Pubkey,
+    pub jackpot_pool: Pubkey,     // Default when the game has no progressive jackpot
+    pub outstanding_liability: u64, // Worst-case payouts reserved for unsettled commitments
+    pub reveal_deadline_slots: u64, // Commitments unrevealed for this many slots can be expired
+    pub expiry_policy: ExpiryPolicy,
+    pub bet_mint: Pubkey,         // Default for native SOL bets, otherwise the SPL token mint
+    pub min_bet_amount: u64,      // In lamports, or the mint's base units for token games
+    pub max_bet_amount: u64,
+    pub min_delay_slots: u64,     // Minimum slots between commit and reveal
+    pub guardian: Pubkey,         // Optional second key allowed to pause; default when unset
+    pub paused: bool,             // Blocks new bets; settlement of open commitments continues
+    pub pending_authority: Pubkey, // Proposed new authority awaiting accept_authority
+    pub randomness_client: Pubkey, // Randomness client settling this game's commitments
+    pub withdrawal_timelock_seconds: i64, // Delay between request_withdrawal and withdraw_treasury
+    pub pending_withdrawal_amount: u64, // Zero when no withdrawal is queued
+    pub withdrawal_unlock_at: i64,
+    pub lp_mint: Pubkey,          // LP share mint; default until create_liquidity_pool
+    pub pending_withdrawal_timelock_seconds: i64, // Queued timelock reduction
+    pub withdrawal_timelock_change_at: i64, // When the queued reduction applies; 0 when none is queued
+}
+
+impl GameConfig {
+    pub fn can_pause(&self, signer: &Pubkey) -> bool {
+        *signer == self.authority || (self.guardian != Pubkey::default() && *signer == self.guardian)
+    }
+
+    pub fn is_token_game(&self) -> bool {
+        self.bet_mint != Pubkey::default()
+    }
+
+    // Applies a queued timelock reduction once the delay it was queued under has passed
+    pub fn apply_timelock_change(&mut self, now: i64) {
+        if self.withdrawal_timelock_change_at != 0 && now >= self.withdrawal_timelock_change_at {
+            self.withdrawal_timelock_seconds = self.pending_withdrawal_timelock_seconds;
+            self.pending_withdrawal_timelock_seconds = 0;
+            self.withdrawal_timelock_change_at = 0;
+        }
+    }
+}
+
+// Lifecycle of an open commitment: Committed -> RandomnessRequested on the oracle path. Settlement
+// and expiry close the account in the same instruction, so closing is the terminal transition and
+// a settled commitment simply no longer exists (its nonce can't be committed again).
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CommitmentStatus {
+    Committed,
+    RandomnessRequested,
+}
+
+// What happens to the bet of a commitment that was never revealed
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ExpiryPolicy {
+    Refund,  // The bet goes back to the player
+    Forfeit, // The treasury keeps the bet
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct PayoutTier {
+    pub threshold: u64,   // Roll (out of odds_denominator) must be strictly greater than this to hit the tier
+    pub multiplier: u64,  // Payout as a multiple of the bet
+    pub label: String,    // Up to MAX_TIER_LABEL_LEN bytes
+    pub jackpot: bool,    // Also pays the progressive jackpot pool
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct SymbolPay {
+    pub symbol: u8,       // Symbol id as used in the reel strips
+    pub count: u8,        // Consecutive matches from the leftmost reel (wilds included)
+    pub multiplier: u64,  // Payout per winning payline as a multiple of the bet
+    pub jackpot: bool,    // Also pays the progressive jackpot pool
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct ScatterPay {
+    pub count: u8,        // Scatter symbols visible anywhere on the grid
+    pub multiplier: u64,  // Payout as a multiple of the bet (may be 0 for a pure bonus)
+    pub free_spins: u8,   // Free spins credited to the player
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default)]
+pub struct ReelConfig {
+    pub rows: u8,                     // Visible rows per reel
+    pub reel_strips: Vec<Vec<u8>>,    // One symbol strip per reel; empty in tier mode
+    pub paylines: Vec<Vec<u8>>,       // Row index on each reel for every payline
+    pub symbol_pays: Vec<SymbolPay>,
+    pub wild_symbol: Option<u8>,      // Substitutes for any symbol except the scatter in line evaluation
+    pub scatter_symbol: Option<u8>,   // Pays anywhere on the grid via scatter_pays
+    pub scatter_pays: Vec<ScatterPay>,
+}
+
+#[account]
+pub struct Paytable {
+    pub game_config: Pubkey,      // 32 bytes
+    pub odds_denominator: u64,    // 8 bytes, e.g. 1_000_000 for ppm odds
+    pub max_rtp_bps: u16,         // 2 bytes
+    pub version: u32,             // 4 bytes
+    pub bump: u8,                 // 1 byte
+    pub tiers: Vec<PayoutTier>,   // Ordered by descending threshold
+    pub reels: ReelConfig,        // Reel mode when reel strips are configured
+}
+
+// Result of a single spin, in either tier or reel mode
+pub struct SpinOutcome {
+    pub roll: u64,        // Tier mode roll in 1..=odds_denominator (0 in reel mode)
+    pub multiplier: u64,  // Total payout multiplier (lines + scatter)
+    pub label: String,
+    pub tier: Option<u8>, // Index of the paytable tier hit; None on a miss and in reel mode
+    pub grid: Vec<u8>,    // Row-major symbol ids (rows x reels); empty in tier mode
+    pub free_spins: u32,  // Free spins awarded by the scatter bonus
+    pub jackpot: bool,    // A jackpot tier or combination hit; the progressive pool is paid too
+}
+
+impl Paytable {
+    pub fn is_reel_mode(&self) -> bool {
+        !self.reels.reel_strips.is_empty()
+    }
+
+    // Returns the multiplier, label and jackpot flag of the first tier the roll beats
+    pub fn evaluate(&self, roll: u64) -> (u64, &str, bool) {
+        self.tiers
+            .iter()
+            .find(|tier| roll > tier.threshold)
+            .map(|tier| (tier.multiplier, tier.label.as_str(), tier.jackpot))
+            .unwrap_or((0, "Try Again!", false))
+    }
+
+    // Largest multiplier a single spin can pay from the treasury (the jackpot pool pays separately)
+    pub fn max_multiplier(&self) -> Result<u64> {
+        if !self.is_reel_mode() {
+            return Ok(self.tiers.iter().map(|tier| tier.multiplier).max().unwrap_or(0));
+        }
+        let best_line = self.reels.symbol_pays.iter().map(|pay| pay.multiplier).max().unwrap_or(0);
+        let best_scatter = self.reels.scatter_pays.iter().map(|pay| pay.multiplier).max().unwrap_or(0);
+        best_line
+            .checked_mul(self.reels.paylines.len() as u64)
+            .and_then(|lines| lines.checked_add(best_scatter))
+            .ok_or(SlotMachineError::MathOverflow.into())
+    }
+
+    // Turns the outcome hash into a spin result using the reels if configured, otherwise the tiers
+    pub fn resolve(&self, hash: [u8; 32]) -> Result<SpinOutcome> {
+        if !self.is_reel_mode() {
+            let roll = draw_roll(hash, self.odds_denominator);
+            let (multiplier, label, jackpot) = self.evaluate(roll);
+            return Ok(SpinOutcome {
+                roll,
+                multiplier,
+                label: label.to_string(),
+                tier: self.tiers.iter().position(|tier| roll > tier.threshold).map(|index| index as u8),
+                grid: Vec::new(),
+                free_spins: 0,
+                jackpot,
+            });
+        }
+
+        let grid = self.reels.spin(hash);
+        let (line_multiplier, jackpot) = self.reels.evaluate_paylines(&grid)?;
+        let (scatter_multiplier, free_spins) = self.reels.evaluate_scatter(&grid);
+        let multiplier = line_multiplier.checked_add(scatter_multiplier).ok_or(SlotMachineError::MathOverflow)?;
+        let label = if jackpot {
+            "JACKPOT!"
+        } else if free_spins > 0 {
+            "Free Spins!"
+        } else if scatter_multiplier > 0 {
+            "Scatter Win!"
+        } else if line_multiplier > 0 {
+            "Line Win!"
+        } else {
+            "Try Again!"
+        };
+        Ok(SpinOutcome {
+            roll: 0,
+            multiplier,
+            label: label.to_string(),
+            tier: None,
+            grid,
+            free_spins: u32::from(free_spins),
+            jackpot,
+        })
+    }
+}
+
+impl ReelConfig {
+    // Picks an independent, unbiased stop for every reel and lays out the visible window
+    pub fn spin(&self, hash: [u8; 32]) -> Vec<u8> {
+        let reels = self.reel_strips.len();
+        let mut grid = vec![0u8; reels * self.rows as usize];
+        for (reel, strip) in self.reel_strips.iter().enumerate() {
+            let reel_hash: [u8; 32] = Sha256::new()
+                .chain_update(hash)
+                .chain_update([reel as u8])
+                .finalize()
+                .into();
+            let stop = (draw_roll(reel_hash, strip.len() as u64) - 1) as usize;
+            for row in 0..self.rows as usize {
+                grid[row * reels + reel] = strip[(stop + row) % strip.len()];
+            }
+        }
+        grid
+    }
+
+    // Sums the pays of every payline. A line pays for the first non-wild symbol on it, over the
+    // run of that symbol and wilds from the left; a line of only wilds pays as the wild symbol.
+    // Also reports whether any line hit a jackpot combination.
+    pub fn evaluate_paylines(&self, grid: &[u8]) -> Result<(u64, bool)> {
+        let reels = self.reel_strips.len();
+        let mut total: u64 = 0;
+        let mut jackpot = false;
+        for payline in &self.paylines {
+            let symbols: Vec<u8> = payline
+                .iter()
+                .enumerate()
+                .map(|(reel, row)| grid[*row as usize * reels + reel])
+                .collect();
+            let target = symbols
+                .iter()
+                .copied()
+                .find(|symbol| Some(*symbol) != self.wild_symbol)
+                .unwrap_or(symbols[0]);
+            if Some(target) == self.scatter_symbol {
+                continue;
+            }
+            let run = symbols
+                .iter()
+                .take_while(|symbol| **symbol == target || Some(**symbol) == self.wild_symbol)
+                .count() as u8;
+            if let Some(pay) = self.line_pay(target, run) {
+                total = total.checked_add(pay.multiplier).ok_or(SlotMachineError::MathOverflow)?;
+                jackpot |= pay.jackpot;
+            }
+        }
+        Ok((total, jackpot))
+    }
+
+    // Scatters pay anywhere on the grid; returns the multiplier and free spins for their count
+    pub fn evaluate_scatter(&self, grid: &[u8]) -> (u64, u8) {
+        let Some(scatter) = self.scatter_symbol else {
+            return (0, 0);
+        };
+        let count = grid.iter().filter(|symbol| **symbol == scatter).count();
+        self.scatter_pay(count)
+            .map(|pay| (pay.multiplier, pay.free_spins))
+            .unwrap_or((0, 0))
+    }
+
+    // The pay for a run is the listed combination with the highest count not exceeding it
+    fn line_pay(&self, symbol: u8, run: u8) -> Option<&SymbolPay> {
+        self.symbol_pays
+            .iter()
+            .filter(|pay| pay.symbol == symbol && pay.count <= run)
+            .max_by_key(|pay| pay.count)
+    }
+
+    fn scatter_pay(&self, count: usize) -> Option<&ScatterPay> {
+        self.scatter_pays
+            .iter()
+            .filter(|pay| pay.count as usize <= count)
+            .max_by_key(|pay| pay.count)
+    }
+}
+
+#[account]
+pub struct Commitment {
+    pub player: Pubkey,           // 32 bytes
+    pub commitment_hash: [u8; 32], // 32 bytes
+    pub bet_amount: u64,          // 8 bytes
     pub timestamp: i64,           // 8 bytes
-    pub revealed: bool,           // 1 byte
+    pub status: CommitmentStatus, // 1 byte, Committed or RandomnessRequested
     pub bump: u8,                 // 1 byte
     pub nonce: u64,                // 8 bytes
-    pub randomness_requested: bool, // 1 byte
+    pub randomness_account: Pubkey, // 32 bytes
+    pub randomness_seed_slot: u64,  // 8 bytes
+    pub free_spin: bool,          // 1 byte, placed with a free spin instead of a new bet
+    pub max_payout: u64,          // 8 bytes, worst-case payout reserved in the treasury
+    pub bet_mint: Pubkey,         // 32 bytes, currency of the bet (default for native SOL)
+    pub game_config: Pubkey,      // 32 bytes, machine the bet was placed on
+    pub paytable_version: u32,    // 4 bytes, paytable version the odds were fixed at
+    pub commit_slot: u64,         // 8 bytes, slot the commitment was placed in
+    pub use_switchboard: bool,    // 1 byte, settlement path fixed at commit time
+    pub entropy_slot: u64,        // 8 bytes, slot whose hash the fallback reveal mixes in
+}
+
+impl Commitment {
+    // Refuses any step that does not start from `expected`, so a commitment can't be settled
+    // through both paths. Settling twice is impossible since settlement closes the account.
+    pub fn require_status(&self, expected: CommitmentStatus) -> Result<()> {
+        if self.status == expected {
+            return Ok(());
+        }
+        match self.status {
+            CommitmentStatus::Committed => err!(SlotMachineError::RandomnessNotRequested),
+            CommitmentStatus::RandomnessRequested => err!(SlotMachineError::RandomnessAlreadyRequested),
+        }
+    }
+
+    // The fallback outcome is fixed once the entropy slot's hash exists, and an oracle outcome
+    // may be public as soon as randomness was requested
+    pub fn is_outcome_known(&self, current_slot: u64) -> bool {
+        match self.status {
+            CommitmentStatus::Committed => !self.use_switchboard && current_slot > self.entropy_slot,
+            CommitmentStatus::RandomnessRequested => true,
+        }
+    }
+
+    // Past the game's reveal deadline the commitment can only be expired. A fallback commitment
+    // also expires once its entropy slot has dropped out of the SlotHashes sysvar.
+    pub fn is_expired(&self, game_config: &GameConfig, current_slot: u64) -> bool {
+        let window_missed =
+            !self.use_switchboard && current_slot.saturating_sub(self.entropy_slot) >= SLOT_HASHES_WINDOW;
+        window_missed || current_slot.saturating_sub(self.commit_slot) >= game_config.reveal_deadline_slots
+    }
+}
+
+// Everything needed to re-derive and audit one settled spin, kept after the commitment closes.
+// Switchboard accounts get reused, so the revealed value is copied here rather than referenced.
+#[account]
+pub struct SpinResult {
+    pub player: Pubkey,
+    pub game_config: Pubkey,
+    pub nonce: u64,
+    pub commitment_hash: [u8; 32],
+    pub secret_value: u64,        // Revealed player secret
+    pub salt: u64,                // Revealed player salt
+    pub bet_amount: u64,
+    pub bet_mint: Pubkey,
+    pub slot: u64,                // Settlement slot
+    pub random_value: u64,
+    pub randomness_account: Pubkey, // Switchboard account used, default for the fallback reveal
+    pub tier: String,             // Paytable tier label, at most MAX_TIER_LABEL_LEN bytes
+    pub multiplier: u64,
+    pub payout: u64,
+    pub jackpot_payout: u64,
+    pub source: SettlementSource,
+    pub settled_at: i64,
+    pub bump: u8,
+    pub hash_slot: u64,           // Slot of the hash mixed into a fallback reveal, 0 for Switchboard
+    pub slot_hash: [u8; 32],      // That slot's hash, zeroed for Switchboard
+    pub oracle_value: [u8; 32],   // Revealed Switchboard value, zeroed for the fallback reveal
+    pub grid: Vec<u8>,            // Row-major symbol ids (rows x reels); empty in tier mode
+    pub rows: u8,                 // Grid shape at settlement, 0 in tier mode
+    pub reels: u8,
+}
+
+impl SpinResult {
+    pub fn record(
+        &mut self,
+        settled: &SpinSettled,
+        commitment: &Commitment,
+        secret_value: u64,
+        salt: u64,
+        randomness_account: Pubkey,
+        bump: u8,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        self.player = settled.player;
+        self.game_config = settled.game_config;
+        self.nonce = commitment.nonce;
+        self.commitment_hash = commitment.commitment_hash;
+        self.secret_value = secret_value;
+        self.salt = salt;
+        self.bet_amount = settled.bet_amount;
+        self.bet_mint = settled.bet_mint;
+        self.slot = clock.slot;
+        self.random_value = settled.random_value;
+        self.randomness_account = randomness_account;
+        self.tier = settled.tier.clone();
+        self.multiplier = settled.multiplier;
+        self.payout = settled.payout;
+        self.jackpot_payout = settled.jackpot_payout;
+        self.source = settled.source;
+        self.settled_at = clock.unix_timestamp;
+        self.bump = bump;
+        Ok(())
+    }
+
+    // Stores the grid with the shape it was drawn on, so clients don't read it against a later paytable
+    pub fn record_grid(&mut self, reels: &ReelConfig, grid: &[u8]) {
+        if !grid.is_empty() {
+            self.rows = reels.rows;
+            self.reels = reels.reel_strips.len() as u8;
+        }
+        self.grid = grid.to_vec();
+    }
+}
+
+// House accounting of one machine, updated in the same instruction as every bet and settlement
+#[account]
+pub struct GameStats {
+    pub game_config: Pubkey,
+    pub bump: u8,
+    pub paytable_version: u32,    // Paytable version tier_hits refer to
+    pub total_wagered: u64,       // Bets committed, settled or not
+    pub settled_wagered: u64,     // Bets of settled paid spins, the RTP denominator
+    pub total_paid: u64,          // Treasury payouts of settled spins, free spins included
+    pub jackpot_paid: u64,        // Progressive jackpot payouts
+    pub total_refunded: u64,      // Bets returned by expire_commitment
+    pub spins: u64,
+    pub misses: u64,              // Spins that paid nothing
+    pub tier_hits: [u64; MAX_PAYTABLE_TIERS], // Hits per paytable tier index (tier mode only)
+    pub outstanding_liability: u64, // Mirror of GameConfig after the last update
+    pub realized_rtp_bps: u64,    // (total_paid + jackpot_paid) / settled_wagered
+}
+
+impl GameStats {
+    pub fn record_bet(&mut self, bet_amount: u64, outstanding_liability: u64) -> Result<()> {
+        self.total_wagered = self.total_wagered.checked_add(bet_amount).ok_or(SlotMachineError::MathOverflow)?;
+        self.outstanding_liability = outstanding_liability;
+        Ok(())
+    }
+
+    pub fn record_spin(
+        &mut self,
+        stake: u64,
+        outcome: &SpinOutcome,
+        paytable_version: u32,
+        payout: u64,
+        jackpot_payout: u64,
+        outstanding_liability: u64,
+    ) -> Result<()> {
+        // Tier indices change meaning with the paytable, so hit counts restart on a new version
+        if paytable_version != self.paytable_version {
+            self.paytable_version = paytable_version;
+            self.tier_hits = [0; MAX_PAYTABLE_TIERS];
+        }
+        if let Some(tier) = outcome.tier {
+            self.tier_hits[tier as usize] = self.tier_hits[tier as usize].saturating_add(1);
+        }
+        if payout == 0 && jackpot_payout == 0 {
+            self.misses = self.misses.saturating_add(1);
+        }
+        self.spins = self.spins.saturating_add(1);
+        self.settled_wagered = self.settled_wagered.checked_add(stake).ok_or(SlotMachineError::MathOverflow)?;
+        self.total_paid = self.total_paid.checked_add(payout).ok_or(SlotMachineError::MathOverflow)?;
+        self.jackpot_paid = self.jackpot_paid.checked_add(jackpot_payout).ok_or(SlotMachineError::MathOverflow)?;
+        self.outstanding_liability = outstanding_liability;
+        self.update_rtp();
+        Ok(())
+    }
+
+    pub fn record_refund(&mut self, refund: u64, outstanding_liability: u64) -> Result<()> {
+        self.total_refunded = self.total_refunded.checked_add(refund).ok_or(SlotMachineError::MathOverflow)?;
+        self.outstanding_liability = outstanding_liability;
+        Ok(())
+    }
+
+    fn update_rtp(&mut self) {
+        if self.settled_wagered == 0 {
+            return;
+        }
+        let returned = self.total_paid as u128 + self.jackpot_paid as u128;
+        self.realized_rtp_bps =
+            u64::try_from(returned * BPS_DENOMINATOR as u128 / self.settled_wagered as u128).unwrap_or(u64::MAX);
+    }
+}
+
+#[account]
+pub struct PlayerState {
+    pub player: Pubkey,           // 32 bytes
+    pub game_config: Pubkey,      // 32 bytes
+    pub free_spins: u32,          // 4 bytes, free spins available to commit
+    pub free_spin_bet: u64,       // 8 bytes, bet level the free spins are played at
+    pub bump: u8,                 // 1 byte
+    pub next_nonce: u64,          // 8 bytes, lowest nonce the next commitment may use
+    pub total_wagered: u64,       // 8 bytes, bets received in the game's currency
+    pub total_won: u64,           // 8 bytes, payouts including jackpots
+    pub spins: u64,               // 8 bytes, settled spins
+    pub biggest_win: u64,         // 8 bytes, largest single-spin payout
+    pub last_played: i64,         // 8 bytes, time of the last commit or settlement
+}
+
+impl PlayerState {
+    // Player state is created by whichever of commit, commit_token, spin or a settlement runs
+    // first for the player (init_if_needed), so only the first call fills in its identity
+    pub fn initialize_if_needed(&mut self, player: Pubkey, game_config: Pubkey, bump: u8) {
+        if self.player == Pubkey::default() {
+            self.player = player;
+            self.game_config = game_config;
+            self.bump = bump;
+        }
+    }
+
+    // Nonces only move forward, so a closed commitment's seeds can never be committed again
+    pub fn use_nonce(&mut self, nonce: u64) -> Result<()> {
+        require!(nonce >= self.next_nonce, SlotMachineError::NonceAlreadyUsed);
+        self.next_nonce = nonce.checked_add(1).ok_or(SlotMachineError::MathOverflow)?;
+        Ok(())
+    }
+
+    pub fn record_wager(&mut self, bet_amount: u64, now: i64) -> Result<()> {
+        self.total_wagered = self.total_wagered.checked_add(bet_amount).ok_or(SlotMachineError::MathOverflow)?;
+        self.last_played = now;
+        Ok(())
+    }
+
+    pub fn record_spin(&mut self, won: u64, now: i64) -> Result<()> {
+        self.spins = self.spins.checked_add(1).ok_or(SlotMachineError::MathOverflow)?;
+        self.total_won = self.total_won.checked_add(won).ok_or(SlotMachineError::MathOverflow)?;
+        self.biggest_win = self.biggest_win.max(won);
+        self.last_played = now;
+        Ok(())
+    }
+
+    // Credits free spins at the triggering bet. Pending free spins at a different bet are all
+    // played at the lower of the two bets so a small trigger can never upgrade a pending bonus.
+    pub fn award_free_spins(&mut self, free_spins: u32, bet_amount: u64) -> Result<()> {
+        self.free_spin_bet = if self.free_spins == 0 {
+            bet_amount
+        } else {
+            self.free_spin_bet.min(bet_amount)
+        };
+        self.free_spins = self.free_spins.checked_add(free_spins).ok_or(SlotMachineError::MathOverflow)?;
+        Ok(())
+    }
+}
+
+#[account]
+pub struct JackpotPool {
+    pub game_config: Pubkey,      // 32 bytes
+    pub contribution_bps: u16,    // 2 bytes, share of every bet fed into the pool
+    pub reseed_amount: u64,       // 8 bytes, left in the pool after a hit
+    pub balance: u64,             // 8 bytes, current jackpot size (excludes rent)
+    pub hits: u64,                // 8 bytes
+    pub last_winner: Pubkey,      // 32 bytes
+    pub last_payout: u64,         // 8 bytes
+    pub bump: u8,                 // 1 byte
 }
 
 #[account]
@@ -524,12 +2653,604 @@ pub struct RandomnessClient {
     pub authority: Pubkey,        // 32 bytes
     pub bump: u8,                // 1 byte
     pub use_switchboard: bool,     // 1 byte
+    pub pending_authority: Pubkey, // 32 bytes, default when no transfer is pending
+}
+
+// What the treasury can pay out: lamports above its rent-exempt reserve for SOL games, the
+// token vault balance for token games
+fn treasury_available(
+    game_config: &GameConfig,
+    treasury: &AccountInfo,
+    token_vault: &Option<InterfaceAccount<TokenAccount>>,
+) -> Result<u64> {
+    if !game_config.is_token_game() {
+        return Ok(treasury.lamports().saturating_sub(Rent::get()?.minimum_balance(0)));
+    }
+    let token_vault = token_vault.as_ref().ok_or(SlotMachineError::MissingTokenAccounts)?;
+    Ok(token_vault.amount)
+}
+
+// Fails if the treasury cannot cover `max_payout` on top of the payouts already reserved for
+// open commitments
+fn check_solvency(game_config: &GameConfig, available: u64, max_payout: u64) -> Result<()> {
+    let liability = game_config
+        .outstanding_liability
+        .checked_add(max_payout)
+        .ok_or(SlotMachineError::MathOverflow)?;
+    require!(liability <= available, SlotMachineError::InsufficientTreasury);
+    Ok(())
+}
+
+// Reserves the worst-case payout of a new commitment
+fn reserve_liability(game_config: &mut GameConfig, available: u64, max_payout: u64) -> Result<()> {
+    check_solvency(game_config, available, max_payout)?;
+    game_config.outstanding_liability += max_payout;
+    Ok(())
+}
+
+// Releases the reservation of a settled commitment
+fn release_liability(game_config: &mut GameConfig, max_payout: u64) {
+    game_config.outstanding_liability = game_config.outstanding_liability.saturating_sub(max_payout);
+}
+
+// Token accounts used to pay a token game out of its vault
+struct TreasuryTokenAccounts<'info> {
+    bet_mint: AccountInfo<'info>,
+    decimals: u8,
+    token_vault: AccountInfo<'info>,
+    player_token_account: AccountInfo<'info>,
+    token_program: AccountInfo<'info>,
+}
+
+// Token games must pass all of their token accounts; SOL games ignore them
+fn treasury_token_accounts<'info>(
+    game_config: &GameConfig,
+    bet_mint: &Option<InterfaceAccount<'info, Mint>>,
+    token_vault: &Option<InterfaceAccount<'info, TokenAccount>>,
+    player_token_account: &Option<InterfaceAccount<'info, TokenAccount>>,
+    token_program: &Option<Interface<'info, TokenInterface>>,
+) -> Result<Option<TreasuryTokenAccounts<'info>>> {
+    if !game_config.is_token_game() {
+        return Ok(None);
+    }
+    match (bet_mint, token_vault, player_token_account, token_program) {
+        (Some(bet_mint), Some(token_vault), Some(player_token_account), Some(token_program)) => Ok(Some(TreasuryTokenAccounts {
+            bet_mint: bet_mint.to_account_info(),
+            decimals: bet_mint.decimals,
+            token_vault: token_vault.to_account_info(),
+            player_token_account: player_token_account.to_account_info(),
+            token_program: token_program.to_account_info(),
+        })),
+        _ => err!(SlotMachineError::MissingTokenAccounts),
+    }
+}
+
+// Moves `amount` from the depositor into the treasury in the game's bet currency
+fn deposit_to_treasury<'info>(
+    treasury: AccountInfo<'info>,
+    depositor: AccountInfo<'info>,
+    token_accounts: Option<TreasuryTokenAccounts<'info>>,
+    system_program: AccountInfo<'info>,
+    amount: u64,
+) -> Result<()> {
+    match token_accounts {
+        Some(token_accounts) => {
+            let transfer_accounts = TransferChecked {
+                from: token_accounts.player_token_account,
+                mint: token_accounts.bet_mint,
+                to: token_accounts.token_vault,
+                authority: depositor,
+            };
+            let transfer_ctx = CpiContext::new(token_accounts.token_program, transfer_accounts);
+            token_interface::transfer_checked(transfer_ctx, amount, token_accounts.decimals)
+        }
+        None => {
+            let transfer_accounts = SystemTransfer { from: depositor, to: treasury };
+            let transfer_ctx = CpiContext::new(system_program, transfer_accounts);
+            system_program::transfer(transfer_ctx, amount)
+        }
+    }
+}
+
+// Pays `amount` from the treasury in the game's bet currency, signed by the treasury PDA
+fn pay_from_treasury<'info>(
+    game_config: &Account<GameConfig>,
+    treasury: AccountInfo<'info>,
+    player: AccountInfo<'info>,
+    token_accounts: Option<TreasuryTokenAccounts<'info>>,
+    system_program: AccountInfo<'info>,
+    amount: u64,
+) -> Result<()> {
+    let game_config_key = game_config.key();
+    let treasury_bump = [game_config.treasury_bump];
+    let treasury_seeds: &[&[u8]] = &[TREASURY_SEED, game_config_key.as_ref(), &treasury_bump];
+    let signer_seeds: &[&[&[u8]]] = &[treasury_seeds];
+
+    match token_accounts {
+        Some(token_accounts) => {
+            let transfer_accounts = TransferChecked {
+                from: token_accounts.token_vault,
+                mint: token_accounts.bet_mint,
+                to: token_accounts.player_token_account,
+                authority: treasury,
+            };
+            let transfer_ctx = CpiContext::new_with_signer(token_accounts.token_program, transfer_accounts, signer_seeds);
+            token_interface::transfer_checked(transfer_ctx, amount, token_accounts.decimals)
+        }
+        None => {
+            let transfer_accounts = SystemTransfer { from: treasury, to: player };
+            let transfer_ctx = CpiContext::new_with_signer(system_program, transfer_accounts, signer_seeds);
+            system_program::transfer(transfer_ctx, amount)
+        }
+    }
+}
+
+// Accounts every settlement path touches once the outcome is known
+struct SettlementAccounts<'a, 'info> {
+    game_config: &'a Account<'info, GameConfig>,
+    game_stats: &'a mut Account<'info, GameStats>,
+    paytable: &'a Account<'info, Paytable>,
+    treasury: &'a SystemAccount<'info>,
+    player: &'a Signer<'info>,
+    jackpot_pool: &'a mut Option<Account<'info, JackpotPool>>,
+    player_state: &'a mut Account<'info, PlayerState>,
+    player_state_bump: u8,
+    bet_mint: &'a Option<InterfaceAccount<'info, Mint>>,
+    token_vault: &'a Option<InterfaceAccount<'info, TokenAccount>>,
+    player_token_account: &'a Option<InterfaceAccount<'info, TokenAccount>>,
+    token_program: &'a Option<Interface<'info, TokenInterface>>,
+    system_program: &'a Program<'info, System>,
+}
+
+// The wager being settled; the legacy spin has no commitment behind it
+struct SettledBet {
+    commitment: Pubkey,
+    bet_mint: Pubkey,
+    bet_amount: u64,
+    free_spin: bool,
+    source: SettlementSource,
+}
+
+impl SettledBet {
+    fn of(commitment: &Account<Commitment>, source: SettlementSource) -> Self {
+        Self {
+            commitment: commitment.key(),
+            bet_mint: commitment.bet_mint,
+            bet_amount: commitment.bet_amount,
+            free_spin: commitment.free_spin,
+            source,
+        }
+    }
+}
+
+// Pays out a resolved spin and records it: reels, line win, jackpot, free spins, player and
+// machine stats, then the SpinSettled event. Liability release stays with the caller.
+fn settle_outcome(accounts: SettlementAccounts, bet: SettledBet, outcome: &SpinOutcome) -> Result<SpinSettled> {
+    let player = accounts.player.to_account_info();
+    if !outcome.grid.is_empty() {
+        emit!(ReelsStopped {
+            player: player.key(),
+            paytable: accounts.paytable.key(),
+            paytable_version: accounts.paytable.version,
+            rows: accounts.paytable.reels.rows,
+            reels: accounts.paytable.reels.reel_strips.len() as u8,
+            grid: outcome.grid.clone(),
+            payout_multiplier: outcome.multiplier,
+        });
+    }
+
+    let payout = bet.bet_amount.checked_mul(outcome.multiplier).ok_or(SlotMachineError::MathOverflow)?;
+    if outcome.multiplier > 0 {
+        let token_accounts = treasury_token_accounts(
+            accounts.game_config,
+            accounts.bet_mint,
+            accounts.token_vault,
+            accounts.player_token_account,
+            accounts.token_program,
+        )?;
+        pay_from_treasury(
+            accounts.game_config,
+            accounts.treasury.to_account_info(),
+            player.clone(),
+            token_accounts,
+            accounts.system_program.to_account_info(),
+            payout,
+        )?;
+
+        msg!("{} You won! Payout: {} ({}x multiplier)", outcome.label, payout, outcome.multiplier);
+    } else {
+        msg!("{} Better luck next time!", outcome.label);
+    }
+
+    let mut jackpot_payout = 0;
+    if outcome.jackpot {
+        require_jackpot_pool(accounts.game_config, accounts.jackpot_pool)?;
+        if let Some(jackpot_pool) = accounts.jackpot_pool.as_mut() {
+            jackpot_payout = pay_jackpot(jackpot_pool, &player)?;
+            msg!("PROGRESSIVE JACKPOT! Pool paid: {} lamports", jackpot_payout);
+        }
+    }
+
+    let player_state = accounts.player_state;
+    player_state.initialize_if_needed(player.key(), accounts.game_config.key(), accounts.player_state_bump);
+    if outcome.free_spins > 0 {
+        player_state.award_free_spins(outcome.free_spins, bet.bet_amount)?;
+        msg!("Bonus! {} free spins awarded ({} available)", outcome.free_spins, player_state.free_spins);
+    }
+    let won = payout.checked_add(jackpot_payout).ok_or(SlotMachineError::MathOverflow)?;
+    player_state.record_spin(won, Clock::get()?.unix_timestamp)?;
+
+    let stake = if bet.free_spin { 0 } else { bet.bet_amount };
+    accounts.game_stats.record_spin(
+        stake,
+        outcome,
+        accounts.paytable.version,
+        payout,
+        jackpot_payout,
+        accounts.game_config.outstanding_liability,
+    )?;
+
+    let settled = SpinSettled {
+        player: player.key(),
+        game_config: accounts.game_config.key(),
+        commitment: bet.commitment,
+        bet_mint: bet.bet_mint,
+        bet_amount: bet.bet_amount,
+        free_spin: bet.free_spin,
+        source: bet.source,
+        random_value: outcome.roll,
+        tier: outcome.label.clone(),
+        multiplier: outcome.multiplier,
+        payout,
+        jackpot_payout,
+        free_spins_awarded: outcome.free_spins,
+    };
+    emit!(settled);
+    Ok(settled)
+}
+
+// Event builders shared by the instructions that emit them
+fn bet_committed(commitment: &Account<Commitment>) -> BetCommitted {
+    BetCommitted {
+        player: commitment.player,
+        game_config: commitment.game_config,
+        commitment: commitment.key(),
+        nonce: commitment.nonce,
+        bet_mint: commitment.bet_mint,
+        bet_amount: commitment.bet_amount,
+        free_spin: commitment.free_spin,
+        max_payout: commitment.max_payout,
+        paytable_version: commitment.paytable_version,
+        timestamp: commitment.timestamp,
+        use_switchboard: commitment.use_switchboard,
+    }
+}
+
+fn config_updated(game_config: &Account<GameConfig>) -> ConfigUpdated {
+    ConfigUpdated {
+        game_config: game_config.key(),
+        authority: game_config.authority,
+        pending_authority: game_config.pending_authority,
+        paytable: game_config.paytable,
+        jackpot_pool: game_config.jackpot_pool,
+        lp_mint: game_config.lp_mint,
+        bet_mint: game_config.bet_mint,
+        min_bet_amount: game_config.min_bet_amount,
+        max_bet_amount: game_config.max_bet_amount,
+        min_delay_slots: game_config.min_delay_slots,
+        reveal_deadline_slots: game_config.reveal_deadline_slots,
+        expiry_policy: game_config.expiry_policy,
+        guardian: game_config.guardian,
+        paused: game_config.paused,
+        withdrawal_timelock_seconds: game_config.withdrawal_timelock_seconds,
+        pending_withdrawal_timelock_seconds: game_config.pending_withdrawal_timelock_seconds,
+        withdrawal_timelock_change_at: game_config.withdrawal_timelock_change_at,
+    }
+}
+
+fn paytable_updated(paytable: &Account<Paytable>, rtp_bps: u64) -> PaytableUpdated {
+    PaytableUpdated {
+        game_config: paytable.game_config,
+        paytable: paytable.key(),
+        version: paytable.version,
+        odds_denominator: paytable.odds_denominator,
+        max_rtp_bps: paytable.max_rtp_bps,
+        rtp_bps,
+        tiers: paytable.tiers.len() as u8,
+        reel_mode: paytable.is_reel_mode(),
+    }
+}
+
+fn jackpot_pool_updated(jackpot_pool: &Account<JackpotPool>) -> JackpotPoolUpdated {
+    JackpotPoolUpdated {
+        game_config: jackpot_pool.game_config,
+        jackpot_pool: jackpot_pool.key(),
+        contribution_bps: jackpot_pool.contribution_bps,
+        reseed_amount: jackpot_pool.reseed_amount,
+        balance: jackpot_pool.balance,
+    }
+}
+
+fn randomness_client_updated(randomness_client: &Account<RandomnessClient>) -> RandomnessClientUpdated {
+    RandomnessClientUpdated {
+        randomness_client: randomness_client.key(),
+        authority: randomness_client.authority,
+        pending_authority: randomness_client.pending_authority,
+        use_switchboard: randomness_client.use_switchboard,
+    }
+}
+
+// A game with a progressive jackpot must always be played with its pool, and only with its pool
+fn require_jackpot_pool(game_config: &GameConfig, jackpot_pool: &Option<Account<JackpotPool>>) -> Result<()> {
+    let expected = (game_config.jackpot_pool != Pubkey::default()).then_some(game_config.jackpot_pool);
+    require!(
+        jackpot_pool.as_ref().map(|pool| pool.key()) == expected,
+        SlotMachineError::InvalidJackpotPool
+    );
+    Ok(())
+}
+
+// Share of a bet that feeds the progressive jackpot
+fn jackpot_contribution(jackpot_pool: &Option<Account<JackpotPool>>, bet_amount: u64) -> Result<u64> {
+    let Some(jackpot_pool) = jackpot_pool else {
+        return Ok(0);
+    };
+    let contribution = u128::from(bet_amount) * u128::from(jackpot_pool.contribution_bps) / u128::from(BPS_DENOMINATOR);
+    u64::try_from(contribution).map_err(|_| SlotMachineError::MathOverflow.into())
+}
+
+// Pays the whole pool except the reseed amount to the winner and returns the amount paid
+fn pay_jackpot<'info>(jackpot_pool: &mut Account<'info, JackpotPool>, winner: &AccountInfo<'info>) -> Result<u64> {
+    let payout = jackpot_pool.balance.saturating_sub(jackpot_pool.reseed_amount);
+    if payout > 0 {
+        // The pool is owned by this program, so lamports can be moved directly
+        **jackpot_pool.to_account_info().try_borrow_mut_lamports()? -= payout;
+        **winner.try_borrow_mut_lamports()? += payout;
+        jackpot_pool.balance -= payout;
+    }
+    jackpot_pool.hits = jackpot_pool.hits.checked_add(1).ok_or(SlotMachineError::MathOverflow)?;
+    jackpot_pool.last_winner = winner.key();
+    jackpot_pool.last_payout = payout;
+    Ok(payout)
+}
+
+// Checks tier ordering and labels, and returns the paytable RTP in basis points
+fn validate_paytable(tiers: &[PayoutTier], odds_denominator: u64, max_rtp_bps: u16) -> Result<u64> {
+    require!(!tiers.is_empty() && tiers.len() <= MAX_PAYTABLE_TIERS, SlotMachineError::InvalidPaytable);
+    require!(odds_denominator > 0 && odds_denominator <= MAX_ODDS_DENOMINATOR, SlotMachineError::InvalidPaytable);
+    require!(u64::from(max_rtp_bps) <= BPS_DENOMINATOR, SlotMachineError::InvalidPaytable);
+
+    // Each tier covers the rolls between its threshold and the previous one, so strictly
+    // descending thresholds below odds_denominator keep the tier probabilities summing to at most 100%
+    let mut previous_threshold = odds_denominator;
+    let mut weighted_return: u128 = 0;
+    for tier in tiers {
+        require!(tier.threshold < previous_threshold, SlotMachineError::InvalidPaytable);
+        require!(tier.multiplier > 0, SlotMachineError::InvalidPaytable);
+        require!(tier.label.len() <= MAX_TIER_LABEL_LEN, SlotMachineError::InvalidPaytable);
+
+        let hits = u128::from(previous_threshold - tier.threshold);
+        weighted_return = hits
+            .checked_mul(u128::from(tier.multiplier))
+            .and_then(|tier_return| weighted_return.checked_add(tier_return))
+            .ok_or(SlotMachineError::MathOverflow)?;
+        previous_threshold = tier.threshold;
+    }
+
+    // Compare exactly (RTP = weighted_return / odds_denominator) rather than against a rounded value
+    let scaled_return = weighted_return
+        .checked_mul(u128::from(BPS_DENOMINATOR))
+        .ok_or(SlotMachineError::MathOverflow)?;
+    require!(
+        scaled_return <= u128::from(max_rtp_bps) * u128::from(odds_denominator),
+        SlotMachineError::RtpTooHigh
+    );
+    Ok((scaled_return / u128::from(odds_denominator)) as u64)
+}
+
+// Checks reel dimensions, paylines, symbol and scatter pays, and returns the exact reel RTP in
+// basis points, including the value of free spins
+fn validate_reels(reels: &ReelConfig, max_rtp_bps: u16) -> Result<u64> {
+    // Empty strips switch the paytable back to tier mode
+    if reels.reel_strips.is_empty() {
+        require!(
+            reels.paylines.is_empty()
+                && reels.symbol_pays.is_empty()
+                && reels.scatter_pays.is_empty()
+                && reels.wild_symbol.is_none()
+                && reels.scatter_symbol.is_none(),
+            SlotMachineError::InvalidReels
+        );
+        return Ok(0);
+    }
+
+    let reel_strips = &reels.reel_strips;
+    let reel_count = reel_strips.len();
+    let rows = reels.rows;
+    require!(reel_count <= MAX_REELS, SlotMachineError::InvalidReels);
+    require!(rows > 0 && rows as usize <= MAX_ROWS, SlotMachineError::InvalidReels);
+    require!(
+        reel_strips.iter().all(|strip| !strip.is_empty() && strip.len() <= MAX_STRIP_LEN),
+        SlotMachineError::InvalidReels
+    );
+    require!(!reels.paylines.is_empty() && reels.paylines.len() <= MAX_PAYLINES, SlotMachineError::InvalidReels);
+    require!(
+        reels.paylines.iter().all(|line| line.len() == reel_count && line.iter().all(|row| *row < rows)),
+        SlotMachineError::InvalidReels
+    );
+    require!(reels.symbol_pays.len() <= MAX_SYMBOL_PAYS, SlotMachineError::InvalidReels);
+    require!(
+        reels.symbol_pays.iter().all(|pay| pay.count > 0
+            && pay.count as usize <= reel_count
+            && pay.multiplier > 0
+            && Some(pay.symbol) != reels.scatter_symbol),
+        SlotMachineError::InvalidReels
+    );
+    require!(
+        reels.wild_symbol.is_none() || reels.wild_symbol != reels.scatter_symbol,
+        SlotMachineError::InvalidReels
+    );
+    require!(
+        reels.scatter_pays.is_empty() || reels.scatter_symbol.is_some(),
+        SlotMachineError::InvalidReels
+    );
+    require!(reels.scatter_pays.len() <= MAX_SCATTER_PAYS, SlotMachineError::InvalidReels);
+    require!(
+        reels.scatter_pays.iter().all(|pay| pay.count > 0
+            && pay.count as usize <= reel_count * rows as usize
+            && (pay.multiplier > 0 || pay.free_spins > 0)),
+        SlotMachineError::InvalidReels
+    );
+
+    // Every reel stops uniformly and independently, so each payline sees one uniformly drawn
+    // symbol per reel. Returns are counted over all stop combinations (the product of the
+    // strip lengths) to keep the RTP exact.
+    let total_stops = reel_strips
+        .iter()
+        .try_fold(1u128, |acc, strip| acc.checked_mul(strip.len() as u128))
+        .ok_or(SlotMachineError::MathOverflow)?;
+    let strip_len = |reel: usize| reel_strips[reel].len() as u128;
+    let symbol_count = |reel: usize, symbol: Option<u8>| {
+        reel_strips[reel].iter().filter(|s| Some(**s) == symbol).count() as u128
+    };
+    let best_line_pay = |symbol: u8, run: usize| {
+        reels.symbol_pays
+            .iter()
+            .filter(|pay| pay.symbol == symbol && pay.count as usize <= run)
+            .max_by_key(|pay| pay.count)
+            .map_or(0, |pay| u128::from(pay.multiplier))
+    };
+
+    let mut symbols: Vec<u8> = reels.symbol_pays.iter().map(|pay| pay.symbol).collect();
+    symbols.sort_unstable();
+    symbols.dedup();
+
+    let mut weighted_line_return: u128 = 0;
+    for symbol in symbols {
+        if Some(symbol) == reels.wild_symbol {
+            // Only a line made entirely of wilds pays as the wild symbol
+            let all_wild = (0..reel_count).map(|reel| symbol_count(reel, reels.wild_symbol)).product::<u128>();
+            weighted_line_return = weighted_line_return
+                .checked_add(all_wild * best_line_pay(symbol, reel_count))
+                .ok_or(SlotMachineError::MathOverflow)?;
+            continue;
+        }
+
+        // A run of exactly k: the first k reels show the symbol or a wild (with at least one
+        // real symbol, so the symbol is the line's target), and reel k shows something else
+        let mut symbol_or_wild: u128 = 1;
+        let mut only_wild: u128 = 1;
+        for k in 1..=reel_count {
+            let matching = symbol_count(k - 1, Some(symbol)) + symbol_count(k - 1, reels.wild_symbol);
+            symbol_or_wild *= matching;
+            only_wild *= symbol_count(k - 1, reels.wild_symbol);
+            let tail: u128 = if k < reel_count {
+                let breaking = strip_len(k) - symbol_count(k, Some(symbol)) - symbol_count(k, reels.wild_symbol);
+                (k + 1..reel_count).map(strip_len).product::<u128>() * breaking
+            } else {
+                1
+            };
+            let exactly_k = (symbol_or_wild - only_wild) * tail;
+            weighted_line_return = exactly_k
+                .checked_mul(best_line_pay(symbol, k))
+                .and_then(|line_return| weighted_line_return.checked_add(line_return))
+                .ok_or(SlotMachineError::MathOverflow)?;
+        }
+    }
+
+    // Scatter counts: per reel, count how many stops show j scatters in the visible window,
+    // then convolve across reels to get the number of stop combinations per grid-wide count
+    let mut scatter_return: u128 = 0;
+    let mut weighted_free_spins: u128 = 0;
+    if reels.scatter_symbol.is_some() {
+        let mut combinations = vec![1u128];
+        for strip in reel_strips {
+            let mut per_stop = vec![0u128; rows as usize + 1];
+            for stop in 0..strip.len() {
+                let visible = (0..rows as usize)
+                    .filter(|row| Some(strip[(stop + row) % strip.len()]) == reels.scatter_symbol)
+                    .count();
+                per_stop[visible] += 1;
+            }
+            let mut next = vec![0u128; combinations.len() + rows as usize];
+            for (seen, ways) in combinations.iter().enumerate() {
+                for (visible, stops) in per_stop.iter().enumerate() {
+                    next[seen + visible] += ways * stops;
+                }
+            }
+            combinations = next;
+        }
+        for (count, ways) in combinations.iter().enumerate() {
+            if let Some(pay) = reels.scatter_pays
+                .iter()
+                .filter(|pay| pay.count as usize <= count)
+                .max_by_key(|pay| pay.count)
+            {
+                scatter_return += ways * u128::from(pay.multiplier);
+                weighted_free_spins += ways * u128::from(pay.free_spins);
+            }
+        }
+    }
+
+    // Each free spin is another spin at the same bet, so RTP = base / (1 - expected free spins)
+    require!(weighted_free_spins < total_stops, SlotMachineError::InvalidReels);
+    let scaled_return = weighted_line_return
+        .checked_mul(reels.paylines.len() as u128)
+        .and_then(|line_return| line_return.checked_add(scatter_return))
+        .and_then(|base_return| base_return.checked_mul(u128::from(BPS_DENOMINATOR)))
+        .ok_or(SlotMachineError::MathOverflow)?;
+    let effective_stops = total_stops - weighted_free_spins;
+    require!(
+        scaled_return <= u128::from(max_rtp_bps) * effective_stops,
+        SlotMachineError::RtpTooHigh
+    );
+    Ok((scaled_return / effective_stops) as u64)
+}
+
+// Draws a uniform roll in 1..=denominator from a 256-bit hash without modulo bias.
+// Each 64-bit word is accepted only if it falls below the largest multiple of the
+// denominator; if all four words are rejected the hash is re-hashed and sampling continues.
+fn draw_roll(hash: [u8; 32], denominator: u64) -> u64 {
+    let zone = u64::MAX - (u64::MAX % denominator + 1) % denominator;
+    let mut hash = hash;
+    loop {
+        for word in hash.chunks_exact(8) {
+            let value = u64::from_le_bytes(word.try_into().unwrap());
+            if value <= zone {
+                return value % denominator + 1;
+            }
+        }
+        hash = Sha256::digest(hash).into();
+    }
+}
+
+// Randomness accounts must belong to the Switchboard on-demand program (mainnet or devnet)
+fn is_switchboard_program(owner: &Pubkey) -> bool {
+    *owner == ON_DEMAND_MAINNET_PID || *owner == ON_DEMAND_DEVNET_PID
+}
+
+// Reads the SlotHashes entry of the first slot produced at or after `slot`; skipped slots have
+// no entry. The sysvar is too large to deserialize on-chain, so entries are read straight from
+// its layout: a u64 entry count followed by (u64 slot, [u8; 32] hash) pairs, newest first.
+fn slot_hash_at(slot_hashes: &AccountInfo, slot: u64) -> Result<(u64, [u8; 32])> {
+    let data = slot_hashes.try_borrow_data()?;
+    require!(data.len() >= 8, SlotMachineError::SlotHashUnavailable);
+    let entries = u64::from_le_bytes(data[0..8].try_into().unwrap()) as usize;
+    let mut found = None;
+    for entry in data[8..].chunks_exact(8 + 32).take(entries) {
+        let entry_slot = u64::from_le_bytes(entry[0..8].try_into().unwrap());
+        if entry_slot < slot {
+            break;
+        }
+        found = Some((entry_slot, entry[8..40].try_into().unwrap()));
+    }
+    found.ok_or_else(|| error!(SlotMachineError::SlotHashUnavailable))
 }
 
 // Custom error types
 #[error_code]
 pub enum SlotMachineError {
-    #[msg("Commitment has already been revealed")]
+    #[msg("Commitment has already been settled")]
     AlreadyRevealed,
     #[msg("Insufficient delay between commit and reveal")]
     InsufficientDelay,
@@ -545,4 +3266,265 @@ pub enum SlotMachineError {
     BetTooLow,
     #[msg("Bet amount is too high")]
     BetTooHigh,
+    #[msg("Randomness account is not a valid Switchboard account for this commitment")]
+    InvalidRandomnessAccount,
+    #[msg("Randomness account is stale or does not match the requested seed slot")]
+    RandomnessExpired,
+    #[msg("Randomness has already been revealed")]
+    RandomnessAlreadyRevealed,
+    #[msg("Randomness has not been revealed yet")]
+    RandomnessNotResolved,
+    #[msg("Only the authority can perform this action")]
+    Unauthorized,
+    #[msg("Commitment was placed in Switchboard oracle-only mode; fallback reveal is disabled")]
+    SwitchboardRequired,
+    #[msg("Commitment was placed without Switchboard oracle mode")]
+    SwitchboardDisabled,
+    #[msg("Paytable is invalid or does not belong to this game")]
+    InvalidPaytable,
+    #[msg("Paytable RTP exceeds the configured ceiling")]
+    RtpTooHigh,
+    #[msg("Arithmetic overflow")]
+    MathOverflow,
+    #[msg("Reel strips, paylines or symbol pays are invalid")]
+    InvalidReels,
+    #[msg("No free spins available")]
+    NoFreeSpins,
+    #[msg("Jackpot pool is missing or does not belong to this game")]
+    InvalidJackpotPool,
+    #[msg("Jackpot contribution is too high")]
+    InvalidJackpotConfig,
+    #[msg("Treasury cannot cover the worst-case payout of this bet")]
+    InsufficientTreasury,
+    #[msg("Reveal deadline must exceed the minimum reveal delay")]
+    InvalidExpiryConfig,
+    #[msg("Commitment is past its reveal deadline")]
+    CommitmentExpired,
+    #[msg("Commitment has not reached its reveal deadline")]
+    CommitmentNotExpired,
+    #[msg("Commitment nonce has already been used")]
+    NonceAlreadyUsed,
+    #[msg("Instruction does not match the game's bet currency")]
+    WrongBetCurrency,
+    #[msg("Token games require the bet mint, token vault, player token account and token program")]
+    MissingTokenAccounts,
+    #[msg("Token account does not match the game's bet mint or owner")]
+    InvalidTokenAccount,
+    #[msg("Bet currency can only be changed on a SOL game with no open commitments")]
+    BetCurrencyLocked,
+    #[msg("Progressive jackpots are only available for SOL games")]
+    JackpotRequiresSol,
+    #[msg("Bet mint uses a Token-2022 extension the vault cannot support")]
+    UnsupportedMintExtension,
+    #[msg("Commitment belongs to a different game")]
+    InvalidGameConfig,
+    #[msg("Paytable changed since the commitment was placed")]
+    PaytableChanged,
+    #[msg("Bet limits or reveal delay out of range")]
+    InvalidConfig,
+    #[msg("Machine is paused")]
+    MachinePaused,
+    #[msg("Randomness client does not belong to this game")]
+    InvalidRandomnessClient,
+    #[msg("No treasury withdrawal has been requested")]
+    NoPendingWithdrawal,
+    #[msg("Treasury withdrawal is still time-locked")]
+    WithdrawalLocked,
+    #[msg("Liquidity pool is missing or does not belong to this game")]
+    InvalidLiquidityPool,
+    #[msg("Machine has a liquidity pool; the operator withdraws through its LP shares")]
+    LiquidityPoolActive,
+    #[msg("Amount is too small to mint or redeem a share")]
+    LiquidityTooSmall,
+    #[msg("Slot hash of the entropy slot is not available yet")]
+    SlotHashUnavailable,
+    #[msg("Paytable cannot change while commitments are open")]
+    PaytableLocked,
+    #[msg("Liquidity can only be deposited or redeemed while no commitments are open")]
+    LiquidityLocked,
+    #[msg("Withdraw the SOL treasury before switching the game to token bets")]
+    TreasuryNotEmpty,
+}
+
+// Emitted when a bet or free spin is committed
+#[event]
+pub struct BetCommitted {
+    pub player: Pubkey,
+    pub game_config: Pubkey,
+    pub commitment: Pubkey,
+    pub nonce: u64,
+    pub bet_mint: Pubkey,         // Default for native SOL
+    pub bet_amount: u64,
+    pub free_spin: bool,
+    pub max_payout: u64,          // Liability reserved for the commitment
+    pub paytable_version: u32,
+    pub timestamp: i64,
+    pub use_switchboard: bool,    // Settlement path the commitment must use
+}
+
+// Emitted when a Switchboard randomness account is bound to a commitment
+#[event]
+pub struct RandomnessRequested {
+    pub player: Pubkey,
+    pub game_config: Pubkey,
+    pub commitment: Pubkey,
+    pub randomness_account: Pubkey,
+    pub seed_slot: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SettlementSource {
+    Switchboard,  // consume_randomness
+    CommitReveal, // reveal_and_spin fallback
+    LegacySpin,   // deprecated spin
+}
+
+// Emitted by every settled spin with the full outcome
+#[event]
+pub struct SpinSettled {
+    pub player: Pubkey,
+    pub game_config: Pubkey,
+    pub commitment: Pubkey,       // Default for the legacy spin
+    pub bet_mint: Pubkey,
+    pub bet_amount: u64,
+    pub free_spin: bool,
+    pub source: SettlementSource,
+    pub random_value: u64,
+    pub tier: String,             // Paytable tier label
+    pub multiplier: u64,
+    pub payout: u64,              // Zero on a loss
+    pub jackpot_payout: u64,      // Progressive jackpot paid on top, in lamports
+    pub free_spins_awarded: u32,
+}
+
+// Emitted when expire_commitment closes a commitment
+#[event]
+pub struct CommitmentRefunded {
+    pub player: Pubkey,
+    pub game_config: Pubkey,
+    pub commitment: Pubkey,
+    pub bet_mint: Pubkey,
+    pub refund: u64,              // Zero when the bet was forfeited
+}
+
+// Snapshot of a machine's settings, emitted by every instruction that changes them
+#[event]
+pub struct ConfigUpdated {
+    pub game_config: Pubkey,
+    pub authority: Pubkey,
+    pub pending_authority: Pubkey,
+    pub paytable: Pubkey,
+    pub jackpot_pool: Pubkey,
+    pub lp_mint: Pubkey,
+    pub bet_mint: Pubkey,
+    pub min_bet_amount: u64,
+    pub max_bet_amount: u64,
+    pub min_delay_slots: u64,
+    pub reveal_deadline_slots: u64,
+    pub expiry_policy: ExpiryPolicy,
+    pub guardian: Pubkey,
+    pub paused: bool,
+    pub withdrawal_timelock_seconds: i64,
+    pub pending_withdrawal_timelock_seconds: i64,
+    pub withdrawal_timelock_change_at: i64, // Zero when no timelock reduction is queued
+}
+
+// Emitted when a paytable is created, its tiers change or its reels are configured
+#[event]
+pub struct PaytableUpdated {
+    pub game_config: Pubkey,
+    pub paytable: Pubkey,
+    pub version: u32,             // Commitments placed at an older version can only be refunded
+    pub odds_denominator: u64,
+    pub max_rtp_bps: u16,
+    pub rtp_bps: u64,             // RTP of the part that changed: the tiers, or the reels in configure_reels
+    pub tiers: u8,
+    pub reel_mode: bool,
+}
+
+// Emitted when a jackpot pool is created or its contribution and reseed change
+#[event]
+pub struct JackpotPoolUpdated {
+    pub game_config: Pubkey,
+    pub jackpot_pool: Pubkey,
+    pub contribution_bps: u16,
+    pub reseed_amount: u64,
+    pub balance: u64,
+}
+
+// Emitted when a randomness client is created, switches settlement mode or changes authority
+#[event]
+pub struct RandomnessClientUpdated {
+    pub randomness_client: Pubkey,
+    pub authority: Pubkey,
+    pub pending_authority: Pubkey,
+    pub use_switchboard: bool,
+}
+
+// Emitted when the authority queues (or cancels, with amount 0) a treasury withdrawal
+#[event]
+pub struct WithdrawalRequested {
+    pub game_config: Pubkey,
+    pub authority: Pubkey,
+    pub amount: u64,
+    pub unlock_at: i64,
+}
+
+// Emitted when anyone tops up the treasury bankroll
+#[event]
+pub struct TreasuryDeposited {
+    pub game_config: Pubkey,
+    pub depositor: Pubkey,
+    pub bet_mint: Pubkey,         // Default for native SOL
+    pub amount: u64,
+}
+
+// Emitted when the authority's timelocked withdrawal is paid out
+#[event]
+pub struct TreasuryWithdrawn {
+    pub game_config: Pubkey,
+    pub authority: Pubkey,
+    pub bet_mint: Pubkey,
+    pub amount: u64,
+}
+
+// Emitted when LP shares are minted against a deposit
+#[event]
+pub struct LiquidityDeposited {
+    pub game_config: Pubkey,
+    pub provider: Pubkey,
+    pub amount: u64,              // Amount the treasury received, after any transfer fee
+    pub shares: u64,
+    pub nav: u64,                 // Treasury NAV after the deposit
+}
+
+// Emitted when LP shares are burned for their cut of the treasury
+#[event]
+pub struct LiquidityWithdrawn {
+    pub game_config: Pubkey,
+    pub provider: Pubkey,
+    pub amount: u64,
+    pub shares: u64,
+    pub nav: u64,                 // Treasury NAV after the withdrawal
+}
+
+// Emitted when a player closes a spin result to reclaim its rent
+#[event]
+pub struct SpinResultClosed {
+    pub player: Pubkey,
+    pub game_config: Pubkey,
+    pub spin_result: Pubkey,
+    pub nonce: u64,
+}
+
+// Emitted by reel-mode settlements so clients can render exactly the grid the chain decided
+#[event]
+pub struct ReelsStopped {
+    pub player: Pubkey,
+    pub paytable: Pubkey,
+    pub paytable_version: u32,
+    pub rows: u8,
+    pub reels: u8,
+    pub grid: Vec<u8>,            // Row-major symbol ids (rows x reels)
+    pub payout_multiplier: u64,
 }
\ No newline at end of file
@@ -1,7 +1,7 @@
 import * as anchor from "@coral-xyz/anchor";
 import { Program } from "@coral-xyz/anchor";
 import { SlotMachine } from "../target/types/slot_machine";
-import { PublicKey, SystemProgram, Keypair, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import { PublicKey, SystemProgram, Keypair, LAMPORTS_PER_SOL, SYSVAR_SLOT_HASHES_PUBKEY, Transaction, TransactionInstruction } from "@solana/web3.js";
 import { expect } from "chai";
 import { createHash } from "crypto";
 import { BN } from "@coral-xyz/anchor";
@@ -9,6 +9,86 @@ import crypto from "crypto";
 
 const TREASURY_SEED = Buffer.from("treasury");
 const GAME_CONFIG_SEED = Buffer.from("game_config");
+const MACHINE_ID = new BN(0);
+const PAYTABLE_SEED = Buffer.from("paytable");
+const PLAYER_STATE_SEED = Buffer.from("player_state");
+const JACKPOT_SEED = Buffer.from("jackpot");
+const JACKPOT_CONTRIBUTION_BPS = 100; // 1% of every bet
+const JACKPOT_RESEED = new BN(50_000_000); // 0.05 SOL
+const TOKEN_VAULT_SEED = Buffer.from("token_vault");
+const LP_MINT_SEED = Buffer.from("lp_mint");
+const SPIN_RESULT_SEED = Buffer.from("spin_result");
+const GAME_STATS_SEED = Buffer.from("game_stats");
+const TOKEN_PROGRAM_ID = new PublicKey("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+const TOKEN_2022_PROGRAM_ID = new PublicKey("TokenzQdBNbLqP5VEhdkAS6EHFLe1jbKpSyvCgH7uNaP");
+// SOL games leave the optional token accounts out
+const NO_TOKEN_ACCOUNTS = { betMint: null, tokenVault: null, playerTokenAccount: null, tokenProgram: null };
+
+// Test paytable in parts-per-million: a roll in 1..=1_000_000 strictly above the
+// threshold hits the tier (RTP 95.5%)
+const ODDS_DENOMINATOR = new BN(1_000_000);
+const PAYOUT_TIERS = [
+  { threshold: new BN(995_000), multiplier: new BN(25), label: "JACKPOT!", jackpot: true },    // 0.5%
+  { threshold: new BN(980_000), multiplier: new BN(10), label: "Big Win!", jackpot: false },   // 1.5%
+  { threshold: new BN(950_000), multiplier: new BN(6), label: "Great!", jackpot: false },      // 3%
+  { threshold: new BN(900_000), multiplier: new BN(3), label: "Nice!", jackpot: false },       // 5%
+  { threshold: new BN(800_000), multiplier: new BN(2), label: "Win!", jackpot: false },        // 10%
+  { threshold: new BN(650_000), multiplier: new BN(1), label: "Break Even", jackpot: false },  // 15%
+];
+const MAX_RTP_BPS = 9600;
+
+// Test reels: 3 reels x 3 rows over symbols 0-6 (🍒 🍋 🍊 🍇 🔔 💎 7️⃣), paying the
+// three horizontal lines (RTP 68.1%)
+const REEL_ROWS = 3;
+const SCATTER_SYMBOL = 7; // ⭐
+const REEL_STRIPS = [
+  Buffer.from([0, 0, 0, 1, 1, 2, 3, 4, 5, 6]),
+  Buffer.from([0, 0, 0, 1, 1, 2, 3, 4, 5, 6]),
+  Buffer.from([0, 0, 0, 1, 1, 2, 3, 4, 5, 6]),
+];
+const REEL_PAYLINES = [Buffer.from([0, 0, 0]), Buffer.from([1, 1, 1]), Buffer.from([2, 2, 2])];
+const REEL_SYMBOL_PAYS = [
+  { symbol: 0, count: 2, multiplier: new BN(1), jackpot: false },
+  { symbol: 0, count: 3, multiplier: new BN(2), jackpot: false },
+  { symbol: 1, count: 3, multiplier: new BN(4), jackpot: false },
+  { symbol: 2, count: 3, multiplier: new BN(8), jackpot: false },
+  { symbol: 3, count: 3, multiplier: new BN(10), jackpot: false },
+  { symbol: 4, count: 3, multiplier: new BN(15), jackpot: false },
+  { symbol: 5, count: 3, multiplier: new BN(20), jackpot: false },
+  { symbol: 6, count: 3, multiplier: new BN(25), jackpot: true },
+];
+
+// Reel config over the test strips; scatter pays also set the scatter symbol
+function reelConfig(
+  symbolPays = REEL_SYMBOL_PAYS,
+  scatterPays: { count: number; multiplier: BN; freeSpins: number }[] = []
+) {
+  return {
+    rows: REEL_ROWS,
+    reelStrips: REEL_STRIPS,
+    paylines: REEL_PAYLINES,
+    symbolPays,
+    wildSymbol: null,
+    scatterSymbol: scatterPays.length > 0 ? SCATTER_SYMBOL : null,
+    scatterPays,
+  };
+}
+
+// Empty strips switch the paytable back to tier mode
+const TIER_MODE_REELS = {
+  rows: 0,
+  reelStrips: [],
+  paylines: [],
+  symbolPays: [],
+  wildSymbol: null,
+  scatterSymbol: null,
+  scatterPays: [],
+};
+
+// Mock Switchboard randomness account loaded from tests/fixtures/mock_randomness.json.
+// It uses the on-demand RandomnessAccountData layout but its seed slot is long past,
+// so the program must treat it as stale.
+const MOCK_RANDOMNESS = new PublicKey("EA3WGH3UvAPxTeKfRAz5Ytz31SmU9JDK9w2Ev8D5PXMa");
 
 // Helper function to create commitment hash (matches the Rust implementation)
 function createCommitmentHash(secretValue: number, salt: number, playerPubkey: PublicKey): Buffer {
@@ -28,6 +108,18 @@ function createCommitmentHash(secretValue: number, salt: number, playerPubkey: P
   return hash.digest();
 }
 
+// Game configs are seeded by authority and machine id; each machine has its own treasury
+function findGameConfig(programId: PublicKey, authority: PublicKey, machineId: BN): PublicKey {
+  return PublicKey.findProgramAddressSync(
+    [GAME_CONFIG_SEED, authority.toBuffer(), machineId.toArrayLike(Buffer, 'le', 8)],
+    programId
+  )[0];
+}
+
+function findTreasury(programId: PublicKey, gameConfig: PublicKey): [PublicKey, number] {
+  return PublicKey.findProgramAddressSync([TREASURY_SEED, gameConfig.toBuffer()], programId);
+}
+
 // Helper to wait for specified seconds
 function sleep(seconds: number): Promise<void> {
   return new Promise(resolve => setTimeout(resolve, seconds * 1000));
@@ -44,6 +136,9 @@ describe("slot_machine with Switchboard integration", () => {
   let treasury: PublicKey;
   let treasuryBump: number;
   let randomnessClient: PublicKey;
+  let paytable: PublicKey;
+  let playerState: PublicKey;
+  let jackpotPool: PublicKey;
 
   // Helper to airdrop SOL
   async function airdrop(pubkey: PublicKey, amountSol: number) {
@@ -51,6 +146,208 @@ describe("slot_machine with Switchboard integration", () => {
     await provider.connection.confirmTransaction(sig);
   }
 
+  // Minimal SPL token helpers (InitializeTransferFeeConfig, InitializeMint2, InitializeAccount3
+  // and MintTo). Token-2022 mints can carry a transfer fee in basis points.
+  async function createMint(
+    mintAuthority: Keypair,
+    decimals: number,
+    tokenProgram = TOKEN_PROGRAM_ID,
+    transferFeeBps = 0
+  ): Promise<PublicKey> {
+    const mint = Keypair.generate();
+    // Base mint padded to the account length, account type byte, then the TransferFeeConfig TLV entry
+    const space = transferFeeBps > 0 ? 165 + 1 + 4 + 108 : 82;
+    const tx = new Transaction().add(
+      SystemProgram.createAccount({
+        fromPubkey: mintAuthority.publicKey,
+        newAccountPubkey: mint.publicKey,
+        lamports: await provider.connection.getMinimumBalanceForRentExemption(space),
+        space,
+        programId: tokenProgram,
+      })
+    );
+    if (transferFeeBps > 0) {
+      const feeBps = Buffer.alloc(2);
+      feeBps.writeUInt16LE(transferFeeBps);
+      tx.add(
+        new TransactionInstruction({
+          programId: tokenProgram,
+          keys: [{ pubkey: mint.publicKey, isSigner: false, isWritable: true }],
+          data: Buffer.concat([
+            Buffer.from([26, 0, 1]),
+            mintAuthority.publicKey.toBuffer(),
+            Buffer.from([0]),
+            feeBps,
+            new BN("18446744073709551615").toArrayLike(Buffer, 'le', 8),
+          ]),
+        })
+      );
+    }
+    tx.add(
+      new TransactionInstruction({
+        programId: tokenProgram,
+        keys: [{ pubkey: mint.publicKey, isSigner: false, isWritable: true }],
+        data: Buffer.concat([Buffer.from([20, decimals]), mintAuthority.publicKey.toBuffer(), Buffer.from([0])]),
+      })
+    );
+    await provider.sendAndConfirm(tx, [mintAuthority, mint]);
+    return mint.publicKey;
+  }
+
+  async function createTokenAccount(owner: Keypair, mint: PublicKey, tokenProgram = TOKEN_PROGRAM_ID, transferFee = false): Promise<PublicKey> {
+    const account = Keypair.generate();
+    // Accounts of transfer-fee mints carry a TransferFeeAmount TLV entry
+    const space = transferFee ? 165 + 1 + 4 + 8 : 165;
+    const tx = new Transaction().add(
+      SystemProgram.createAccount({
+        fromPubkey: owner.publicKey,
+        newAccountPubkey: account.publicKey,
+        lamports: await provider.connection.getMinimumBalanceForRentExemption(space),
+        space,
+        programId: tokenProgram,
+      }),
+      new TransactionInstruction({
+        programId: tokenProgram,
+        keys: [
+          { pubkey: account.publicKey, isSigner: false, isWritable: true },
+          { pubkey: mint, isSigner: false, isWritable: false },
+        ],
+        data: Buffer.concat([Buffer.from([18]), owner.publicKey.toBuffer()]),
+      })
+    );
+    await provider.sendAndConfirm(tx, [owner, account]);
+    return account.publicKey;
+  }
+
+  async function mintTo(mintAuthority: Keypair, mint: PublicKey, destination: PublicKey, amount: BN, tokenProgram = TOKEN_PROGRAM_ID) {
+    const tx = new Transaction().add(
+      new TransactionInstruction({
+        programId: tokenProgram,
+        keys: [
+          { pubkey: mint, isSigner: false, isWritable: true },
+          { pubkey: destination, isSigner: false, isWritable: true },
+          { pubkey: mintAuthority.publicKey, isSigner: true, isWritable: false },
+        ],
+        data: Buffer.concat([Buffer.from([7]), amount.toArrayLike(Buffer, 'le', 8)]),
+      })
+    );
+    await provider.sendAndConfirm(tx, [mintAuthority]);
+  }
+
+  // Sets up a second operator's game betting in a fresh mint, with a funded vault and player
+  async function createTokenGame(tokenProgram = TOKEN_PROGRAM_ID, transferFeeBps = 0) {
+    const tokenAuthority = Keypair.generate();
+    await airdrop(tokenAuthority.publicKey, 5);
+    const tokenGameConfig = findGameConfig(program.programId, tokenAuthority.publicKey, MACHINE_ID);
+    const [tokenTreasury] = findTreasury(program.programId, tokenGameConfig);
+    const [tokenRandomnessClient] = PublicKey.findProgramAddressSync(
+      [Buffer.from("randomness"), tokenAuthority.publicKey.toBuffer()],
+      program.programId
+    );
+    const [tokenPaytable] = PublicKey.findProgramAddressSync([PAYTABLE_SEED, tokenGameConfig.toBuffer()], program.programId);
+    const [tokenPlayerState] = PublicKey.findProgramAddressSync(
+      [PLAYER_STATE_SEED, tokenGameConfig.toBuffer(), player.publicKey.toBuffer()],
+      program.programId
+    );
+    const [tokenVault] = PublicKey.findProgramAddressSync([TOKEN_VAULT_SEED, tokenGameConfig.toBuffer()], program.programId);
+
+    await program.methods.initialize(MACHINE_ID).accounts({
+      gameConfig: tokenGameConfig,
+      treasury: tokenTreasury,
+      authority: tokenAuthority.publicKey,
+      systemProgram: SystemProgram.programId,
+    } as any).signers([tokenAuthority]).rpc();
+    await program.methods.initRandomnessClient().accounts({
+      authority: tokenAuthority.publicKey,
+      randomnessClient: tokenRandomnessClient,
+      systemProgram: SystemProgram.programId,
+    } as any).signers([tokenAuthority]).rpc();
+    await program.methods
+      .createPaytable(PAYOUT_TIERS, ODDS_DENOMINATOR, MAX_RTP_BPS)
+      .accounts({
+        gameConfig: tokenGameConfig,
+        paytable: tokenPaytable,
+        authority: tokenAuthority.publicKey,
+        systemProgram: SystemProgram.programId,
+      } as any)
+      .signers([tokenAuthority])
+      .rpc();
+
+    const mint = await createMint(tokenAuthority, 6, tokenProgram, transferFeeBps);
+    const playerTokenAccount = await createTokenAccount(player, mint, tokenProgram, transferFeeBps > 0);
+    await program.methods
+      .enableTokenBetting()
+      .accounts({
+        gameConfig: tokenGameConfig,
+        betMint: mint,
+        treasury: tokenTreasury,
+        tokenVault,
+        authority: tokenAuthority.publicKey,
+        tokenProgram,
+        systemProgram: SystemProgram.programId,
+      } as any)
+      .signers([tokenAuthority])
+      .rpc();
+    await mintTo(tokenAuthority, mint, tokenVault, new BN(10_000_000_000), tokenProgram);
+    await mintTo(tokenAuthority, mint, playerTokenAccount, new BN(1_000_000_000), tokenProgram);
+
+    return {
+      tokenAuthority,
+      tokenGameConfig,
+      tokenTreasury,
+      tokenRandomnessClient,
+      tokenPaytable,
+      tokenPlayerState,
+      tokenVault,
+      mint,
+      playerTokenAccount,
+    };
+  }
+
+  async function tokenBalance(account: PublicKey): Promise<BN> {
+    return new BN((await provider.connection.getTokenAccountBalance(account)).value.amount);
+  }
+
+  async function eventsOf(signature: string) {
+    const tx = await provider.connection.getTransaction(signature, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+    const parser = new anchor.EventParser(program.programId, program.coder);
+    return Array.from(parser.parseLogs(tx.meta.logMessages));
+  }
+
+  // Opens another machine of the test authority with the test paytable and a funded treasury
+  async function createMachine(machineId: BN) {
+    const machineConfig = findGameConfig(program.programId, authority.publicKey, machineId);
+    const [machineTreasury] = findTreasury(program.programId, machineConfig);
+    const [machinePaytable] = PublicKey.findProgramAddressSync([PAYTABLE_SEED, machineConfig.toBuffer()], program.programId);
+    const [machinePlayerState] = PublicKey.findProgramAddressSync(
+      [PLAYER_STATE_SEED, machineConfig.toBuffer(), player.publicKey.toBuffer()],
+      program.programId
+    );
+
+    await program.methods.initialize(machineId).accounts({
+      gameConfig: machineConfig,
+      treasury: machineTreasury,
+      authority: authority.publicKey,
+      systemProgram: SystemProgram.programId,
+    } as any).signers([authority]).rpc();
+    await program.methods
+      .createPaytable(PAYOUT_TIERS, ODDS_DENOMINATOR, MAX_RTP_BPS)
+      .accounts({
+        gameConfig: machineConfig,
+        paytable: machinePaytable,
+        authority: authority.publicKey,
+        systemProgram: SystemProgram.programId,
+      } as any)
+      .signers([authority])
+      .rpc();
+    await airdrop(machineTreasury, 5);
+
+    return { machineConfig, machineTreasury, machinePaytable, machinePlayerState };
+  }
+
   before(async () => {
     authority = Keypair.generate();
     player = Keypair.generate();
@@ -58,14 +355,8 @@ describe("slot_machine with Switchboard integration", () => {
     await airdrop(player.publicKey, 5);
     
     // Derive PDAs
-    [gameConfig] = PublicKey.findProgramAddressSync([
-      GAME_CONFIG_SEED,
-      authority.publicKey.toBuffer(),
-    ], program.programId);
-    
-    [treasury, treasuryBump] = PublicKey.findProgramAddressSync([
-      TREASURY_SEED
-    ], program.programId);
+    gameConfig = findGameConfig(program.programId, authority.publicKey, MACHINE_ID);
+    [treasury, treasuryBump] = findTreasury(program.programId, gameConfig);
     
     // Derive randomness client PDA
     [randomnessClient] = PublicKey.findProgramAddressSync(
@@ -74,7 +365,7 @@ describe("slot_machine with Switchboard integration", () => {
     );
     
     // Initialize game config
-    await program.methods.initialize().accounts({
+    await program.methods.initialize(MACHINE_ID).accounts({
       gameConfig,
       treasury,
       authority: authority.publicKey,
@@ -92,6 +383,43 @@ describe("slot_machine with Switchboard integration", () => {
       .signers([authority])
       .rpc();
     
+    [playerState] = PublicKey.findProgramAddressSync(
+      [PLAYER_STATE_SEED, gameConfig.toBuffer(), player.publicKey.toBuffer()],
+      program.programId
+    );
+
+    // Create the paytable for this game
+    [paytable] = PublicKey.findProgramAddressSync(
+      [PAYTABLE_SEED, gameConfig.toBuffer()],
+      program.programId
+    );
+    await program.methods
+      .createPaytable(PAYOUT_TIERS, ODDS_DENOMINATOR, MAX_RTP_BPS)
+      .accounts({
+        gameConfig,
+        paytable,
+        authority: authority.publicKey,
+        systemProgram: SystemProgram.programId,
+      } as any)
+      .signers([authority])
+      .rpc();
+    
+    // Create the progressive jackpot pool, seeded by the authority
+    [jackpotPool] = PublicKey.findProgramAddressSync(
+      [JACKPOT_SEED, gameConfig.toBuffer()],
+      program.programId
+    );
+    await program.methods
+      .createJackpotPool(JACKPOT_CONTRIBUTION_BPS, JACKPOT_RESEED)
+      .accounts({
+        gameConfig,
+        jackpotPool,
+        authority: authority.publicKey,
+        systemProgram: SystemProgram.programId,
+      } as any)
+      .signers([authority])
+      .rpc();
+    
     // Fund the treasury for payouts
     await airdrop(treasury, 10);
   });
@@ -100,6 +428,110 @@ describe("slot_machine with Switchboard integration", () => {
     const config = await program.account.gameConfig.fetch(gameConfig);
     expect(config.authority.toBase58()).to.equal(authority.publicKey.toBase58());
     expect(config.treasuryBump).to.equal(treasuryBump);
+    expect(config.machineId.toString()).to.equal(MACHINE_ID.toString());
+    expect(config.paytable.toBase58()).to.equal(paytable.toBase58());
+  });
+
+  it("Creates the jackpot pool with its seed", async () => {
+    const config = await program.account.gameConfig.fetch(gameConfig);
+    expect(config.jackpotPool.toBase58()).to.equal(jackpotPool.toBase58());
+
+    const pool = await program.account.jackpotPool.fetch(jackpotPool);
+    expect(pool.contributionBps).to.equal(JACKPOT_CONTRIBUTION_BPS);
+    expect(pool.balance.toString()).to.equal(JACKPOT_RESEED.toString());
+    expect(pool.hits.toNumber()).to.equal(0);
+  });
+
+  it("Creates the paytable with the configured tiers", async () => {
+    const paytableAccount = await program.account.paytable.fetch(paytable);
+    expect(paytableAccount.gameConfig.toBase58()).to.equal(gameConfig.toBase58());
+    expect(paytableAccount.oddsDenominator.toString()).to.equal(ODDS_DENOMINATOR.toString());
+    expect(paytableAccount.maxRtpBps).to.equal(MAX_RTP_BPS);
+    expect(paytableAccount.version).to.equal(1);
+    expect(paytableAccount.tiers.length).to.equal(PAYOUT_TIERS.length);
+    expect(paytableAccount.tiers[0].label).to.equal("JACKPOT!");
+  });
+
+  it("Rejects paytable updates above the RTP ceiling", async () => {
+    // A 2% jackpot pushes RTP to 118%
+    const generousTiers = [
+      { threshold: new BN(980_000), multiplier: new BN(25), label: "JACKPOT!", jackpot: true },
+      ...PAYOUT_TIERS.slice(2),
+    ];
+    try {
+      await program.methods
+        .updatePaytable(generousTiers, ODDS_DENOMINATOR, MAX_RTP_BPS)
+        .accounts({
+          gameConfig,
+          paytable,
+          authority: authority.publicKey,
+        } as any)
+        .signers([authority])
+        .rpc();
+      expect.fail("Should have thrown an error for RTP above the ceiling");
+    } catch (error) {
+      expect(error.message).to.include("RtpTooHigh");
+    }
+  });
+
+  it("Rejects tiers outside the odds denominator", async () => {
+    const outOfRangeTiers = [
+      { threshold: ODDS_DENOMINATOR, multiplier: new BN(25), label: "JACKPOT!", jackpot: true },
+      ...PAYOUT_TIERS.slice(1),
+    ];
+    try {
+      await program.methods
+        .updatePaytable(outOfRangeTiers, ODDS_DENOMINATOR, MAX_RTP_BPS)
+        .accounts({
+          gameConfig,
+          paytable,
+          authority: authority.publicKey,
+        } as any)
+        .signers([authority])
+        .rpc();
+      expect.fail("Should have thrown an error for a threshold outside the denominator");
+    } catch (error) {
+      expect(error.message).to.include("InvalidPaytable");
+    }
+  });
+
+  it("Rejects paytable updates from non-authority", async () => {
+    try {
+      await program.methods
+        .updatePaytable(PAYOUT_TIERS, ODDS_DENOMINATOR, MAX_RTP_BPS)
+        .accounts({
+          gameConfig,
+          paytable,
+          authority: player.publicKey,
+        } as any)
+        .signers([player])
+        .rpc();
+      expect.fail("Should have thrown an error for non-authority update");
+    } catch (error) {
+      expect(error.message).to.include("Unauthorized");
+    }
+  });
+
+  it("Updates the paytable and bumps its version", async () => {
+    const signature = await program.methods
+      .updatePaytable(PAYOUT_TIERS, ODDS_DENOMINATOR, MAX_RTP_BPS)
+      .accounts({
+        gameConfig,
+        paytable,
+        authority: authority.publicKey,
+      } as any)
+      .signers([authority])
+      .rpc({ commitment: "confirmed" });
+
+    const paytableAccount = await program.account.paytable.fetch(paytable);
+    expect(paytableAccount.version).to.equal(2);
+
+    // Indexers see the new version and RTP without re-reading the account
+    const paytableUpdated = (await eventsOf(signature)).find((event) => event.name === "paytableUpdated");
+    expect(paytableUpdated).to.not.be.undefined;
+    expect(paytableUpdated.data.version).to.equal(2);
+    expect(paytableUpdated.data.rtpBps.toNumber()).to.equal(9550);
+    expect(paytableUpdated.data.reelMode).to.be.false;
   });
 
   it("Verifies randomness client initialization", async () => {
@@ -118,7 +550,7 @@ describe("slot_machine with Switchboard integration", () => {
     const commitmentHash = createCommitmentHash(secretValue, salt, player.publicKey);
 
     const [commitment] = PublicKey.findProgramAddressSync(
-      [Buffer.from("commitment"), player.publicKey.toBuffer(), nonce.toArrayLike(Buffer, 'le', 8)],
+      [Buffer.from("commitment"), gameConfig.toBuffer(), player.publicKey.toBuffer(), nonce.toArrayLike(Buffer, 'le', 8)],
       program.programId
     );
 
@@ -126,9 +558,13 @@ describe("slot_machine with Switchboard integration", () => {
       .commit(Array.from(commitmentHash), betAmount, nonce)
       .accounts({
         gameConfig,
+        randomnessClient,
+        paytable,
         treasury,
         commitment,
+        jackpotPool,
         player: player.publicKey,
+        playerState,
         systemProgram: SystemProgram.programId,
       } as any)
       .signers([player])
@@ -137,12 +573,47 @@ describe("slot_machine with Switchboard integration", () => {
     const commitmentAccount = await program.account.commitment.fetch(commitment);
     expect(commitmentAccount.player.toString()).to.equal(player.publicKey.toString());
     expect(commitmentAccount.betAmount.toString()).to.equal(betAmount.toString());
-    expect(commitmentAccount.revealed).to.be.false;
-    expect(commitmentAccount.randomnessRequested).to.be.false;
+    expect(commitmentAccount.status).to.have.property("committed");
+    // The reveal delay and deadline are counted from the slot the commitment landed in
+    const currentSlot = await provider.connection.getSlot();
+    expect(commitmentAccount.commitSlot.toNumber()).to.be.greaterThan(0);
+    expect(commitmentAccount.commitSlot.toNumber()).to.be.at.most(currentSlot);
     console.log("✅ Commitment created successfully");
   });
 
-  it("Requests randomness (Switchboard integration ready)", async () => {
+  it("Only the authority can toggle Switchboard mode", async () => {
+    try {
+      await program.methods
+        .setUseSwitchboard(true)
+        .accounts({
+          authority: player.publicKey,
+          randomnessClient,
+        } as any)
+        .signers([player])
+        .rpc();
+      expect.fail("Should have thrown an error for non-authority toggle");
+    } catch (error) {
+      expect(error.message).to.match(/ConstraintSeeds|Unauthorized/);
+    }
+
+    const signature = await program.methods
+      .setUseSwitchboard(true)
+      .accounts({
+        authority: authority.publicKey,
+        randomnessClient,
+      } as any)
+      .signers([authority])
+      .rpc({ commitment: "confirmed" });
+
+    const randomnessClientAccount = await program.account.randomnessClient.fetch(randomnessClient);
+    expect(randomnessClientAccount.useSwitchboard).to.be.true;
+    const clientUpdated = (await eventsOf(signature)).find((event) => event.name === "randomnessClientUpdated");
+    expect(clientUpdated).to.not.be.undefined;
+    expect(clientUpdated.data.useSwitchboard).to.be.true;
+    console.log("✅ Switchboard oracle-only mode enabled");
+  });
+
+  it("Rejects stale Switchboard randomness", async () => {
     const nonce = new BN(2);
     const secretValue = crypto.randomInt(100000);
     const salt = crypto.randomInt(100000);
@@ -151,7 +622,7 @@ describe("slot_machine with Switchboard integration", () => {
     const commitmentHash = createCommitmentHash(secretValue, salt, player.publicKey);
 
     const [commitment] = PublicKey.findProgramAddressSync(
-      [Buffer.from("commitment"), player.publicKey.toBuffer(), nonce.toArrayLike(Buffer, 'le', 8)],
+      [Buffer.from("commitment"), gameConfig.toBuffer(), player.publicKey.toBuffer(), nonce.toArrayLike(Buffer, 'le', 8)],
       program.programId
     );
 
@@ -160,9 +631,13 @@ describe("slot_machine with Switchboard integration", () => {
       .commit(Array.from(commitmentHash), betAmount, nonce)
       .accounts({
         gameConfig,
+        randomnessClient,
+        paytable,
         treasury,
         commitment,
+        jackpotPool,
         player: player.publicKey,
+        playerState,
         systemProgram: SystemProgram.programId,
       } as any)
       .signers([player])
@@ -171,25 +646,30 @@ describe("slot_machine with Switchboard integration", () => {
     // Wait for minimum delay
     await sleep(3);
 
-    // Request randomness
-    await program.methods
-      .requestRandomness()
-      .accounts({
-        player: player.publicKey,
-        authority: authority.publicKey,
-        commitment,
-        randomnessClient,
-        systemProgram: SystemProgram.programId,
-      } as any)
-      .signers([player, authority])
-      .rpc();
+    // Request randomness with an account seeded long ago - should fail
+    try {
+      await program.methods
+        .requestRandomness()
+        .accounts({
+          player: player.publicKey,
+          commitment,
+          gameConfig,
+          randomnessAccountData: MOCK_RANDOMNESS,
+          systemProgram: SystemProgram.programId,
+        } as any)
+        .signers([player])
+        .rpc();
+      expect.fail("Should have thrown an error for stale randomness");
+    } catch (error) {
+      expect(error.message).to.include("RandomnessExpired");
+    }
 
     const commitmentAccount = await program.account.commitment.fetch(commitment);
-    expect(commitmentAccount.randomnessRequested).to.be.true;
-    console.log("✅ Randomness requested successfully (Switchboard ready)");
+    expect(commitmentAccount.status).to.have.property("committed");
+    console.log("✅ Stale Switchboard randomness rejected");
   });
 
-  it("Consumes randomness and executes slot machine (Switchboard integration)", async () => {
+  it("Rejects consuming randomness that was never bound to the commitment", async () => {
     const nonce = new BN(3);
     const secretValue = crypto.randomInt(100000);
     const salt = crypto.randomInt(100000);
@@ -198,7 +678,7 @@ describe("slot_machine with Switchboard integration", () => {
     const commitmentHash = createCommitmentHash(secretValue, salt, player.publicKey);
 
     const [commitment] = PublicKey.findProgramAddressSync(
-      [Buffer.from("commitment"), player.publicKey.toBuffer(), nonce.toArrayLike(Buffer, 'le', 8)],
+      [Buffer.from("commitment"), gameConfig.toBuffer(), player.publicKey.toBuffer(), nonce.toArrayLike(Buffer, 'le', 8)],
       program.programId
     );
 
@@ -207,53 +687,116 @@ describe("slot_machine with Switchboard integration", () => {
       .commit(Array.from(commitmentHash), betAmount, nonce)
       .accounts({
         gameConfig,
+        randomnessClient,
+        paytable,
         treasury,
         commitment,
+        jackpotPool,
         player: player.publicKey,
+        playerState,
         systemProgram: SystemProgram.programId,
       } as any)
       .signers([player])
       .rpc();
 
-    // Wait and request randomness
     await sleep(3);
+
+    // Consume with a randomness account that was never requested - should fail
+    try {
+      await program.methods
+        .consumeRandomness(new BN(secretValue), new BN(salt))
+        .accounts({
+          jackpotPool,
+          ...NO_TOKEN_ACCOUNTS,
+          spinResult: null,
+          player: player.publicKey,
+          playerState,
+          commitment,
+          treasury,
+          gameConfig,
+          paytable,
+          randomnessAccountData: MOCK_RANDOMNESS,
+          systemProgram: SystemProgram.programId,
+        } as any)
+        .signers([player])
+        .rpc();
+      expect.fail("Should have thrown an error for unbound randomness");
+    } catch (error) {
+      expect(error.message).to.include("InvalidRandomnessAccount");
+    }
+
+    const commitmentAccount = await program.account.commitment.fetch(commitment);
+    expect(commitmentAccount.status).to.have.property("committed");
+    console.log("✅ Unbound Switchboard randomness rejected");
+  });
+
+  it("Refuses fallback reveal in oracle-only mode", async () => {
+    const nonce = new BN(4);
+    const secretValue = crypto.randomInt(100000);
+    const salt = crypto.randomInt(100000);
+    const betAmount = new BN(10000000); // 0.01 SOL (minimum)
+
+    const commitmentHash = createCommitmentHash(secretValue, salt, player.publicKey);
+
+    const [commitment] = PublicKey.findProgramAddressSync(
+      [Buffer.from("commitment"), gameConfig.toBuffer(), player.publicKey.toBuffer(), nonce.toArrayLike(Buffer, 'le', 8)],
+      program.programId
+    );
+
     await program.methods
-      .requestRandomness()
+      .commit(Array.from(commitmentHash), betAmount, nonce)
       .accounts({
-        player: player.publicKey,
-        authority: authority.publicKey,
-        commitment,
+        gameConfig,
         randomnessClient,
+        paytable,
+        treasury,
+        commitment,
+        jackpotPool,
+        player: player.publicKey,
+        playerState,
         systemProgram: SystemProgram.programId,
       } as any)
-      .signers([player, authority])
+      .signers([player])
       .rpc();
 
-    // Consume randomness (simulate Switchboard callback)
-    const balanceBefore = await provider.connection.getBalance(player.publicKey);
-    
+    await sleep(3);
+
+    try {
+      await program.methods
+        .revealAndSpin(new BN(secretValue), new BN(salt))
+        .accounts({
+          gameConfig,
+          paytable,
+          treasury,
+          commitment,
+          jackpotPool,
+          ...NO_TOKEN_ACCOUNTS,
+          spinResult: null,
+          player: player.publicKey,
+          playerState,
+          systemProgram: SystemProgram.programId,
+        } as any)
+        .signers([player])
+        .rpc();
+      expect.fail("Should have thrown an error for fallback reveal in oracle-only mode");
+    } catch (error) {
+      expect(error.message).to.include("SwitchboardRequired");
+    }
+
+    // Return the machine to fallback mode for the remaining tests
     await program.methods
-      .consumeRandomness(new BN(secretValue), new BN(salt))
+      .setUseSwitchboard(false)
       .accounts({
-        player: player.publicKey,
-        commitment,
-        treasury,
-        gameConfig,
-        systemProgram: SystemProgram.programId,
+        authority: authority.publicKey,
+        randomnessClient,
       } as any)
-      .signers([player])
+      .signers([authority])
       .rpc();
-
-    const commitmentAccount = await program.account.commitment.fetch(commitment);
-    expect(commitmentAccount.revealed).to.be.true;
-
-    const balanceAfter = await provider.connection.getBalance(player.publicKey);
-    console.log(`💰 Balance change: ${(balanceAfter - balanceBefore) / LAMPORTS_PER_SOL} SOL`);
-    console.log("✅ Switchboard-ready randomness consumed successfully");
+    console.log("✅ Fallback reveal refused in oracle-only mode");
   });
 
   it("Falls back to secure randomness without Switchboard", async () => {
-    const nonce = new BN(4);
+    const nonce = new BN(5);
     const secretValue = crypto.randomInt(100000);
     const salt = crypto.randomInt(100000);
     const betAmount = new BN(15000000); // 0.015 SOL
@@ -261,7 +804,7 @@ describe("slot_machine with Switchboard integration", () => {
     const commitmentHash = createCommitmentHash(secretValue, salt, player.publicKey);
 
     const [commitment] = PublicKey.findProgramAddressSync(
-      [Buffer.from("commitment"), player.publicKey.toBuffer(), nonce.toArrayLike(Buffer, 'le', 8)],
+      [Buffer.from("commitment"), gameConfig.toBuffer(), player.publicKey.toBuffer(), nonce.toArrayLike(Buffer, 'le', 8)],
       program.programId
     );
 
@@ -270,9 +813,13 @@ describe("slot_machine with Switchboard integration", () => {
       .commit(Array.from(commitmentHash), betAmount, nonce)
       .accounts({
         gameConfig,
+        randomnessClient,
+        paytable,
         treasury,
         commitment,
+        jackpotPool,
         player: player.publicKey,
+        playerState,
         systemProgram: SystemProgram.programId,
       } as any)
       .signers([player])
@@ -288,16 +835,21 @@ describe("slot_machine with Switchboard integration", () => {
       .revealAndSpin(new BN(secretValue), new BN(salt))
       .accounts({
         gameConfig,
+        paytable,
         treasury,
         commitment,
+        jackpotPool,
+        ...NO_TOKEN_ACCOUNTS,
+        spinResult: null,
         player: player.publicKey,
+        playerState,
         systemProgram: SystemProgram.programId,
       } as any)
       .signers([player])
       .rpc();
 
-    const commitmentAccount = await program.account.commitment.fetch(commitment);
-    expect(commitmentAccount.revealed).to.be.true;
+    // Settlement closes the commitment and returns its rent to the player
+    expect(await provider.connection.getAccountInfo(commitment)).to.be.null;
 
     const balanceAfter = await provider.connection.getBalance(player.publicKey);
     console.log(`💰 Fallback balance change: ${(balanceAfter - balanceBefore) / LAMPORTS_PER_SOL} SOL`);
@@ -305,7 +857,7 @@ describe("slot_machine with Switchboard integration", () => {
   });
 
   it("Prevents double reveal", async () => {
-    const nonce = new BN(5);
+    const nonce = new BN(6);
     const secretValue = crypto.randomInt(100000);
     const salt = crypto.randomInt(100000);
     const betAmount = new BN(10000000); // 0.01 SOL (minimum)
@@ -313,7 +865,7 @@ describe("slot_machine with Switchboard integration", () => {
     const commitmentHash = createCommitmentHash(secretValue, salt, player.publicKey);
 
     const [commitment] = PublicKey.findProgramAddressSync(
-      [Buffer.from("commitment"), player.publicKey.toBuffer(), nonce.toArrayLike(Buffer, 'le', 8)],
+      [Buffer.from("commitment"), gameConfig.toBuffer(), player.publicKey.toBuffer(), nonce.toArrayLike(Buffer, 'le', 8)],
       program.programId
     );
 
@@ -322,9 +874,13 @@ describe("slot_machine with Switchboard integration", () => {
       .commit(Array.from(commitmentHash), betAmount, nonce)
       .accounts({
         gameConfig,
+        randomnessClient,
+        paytable,
         treasury,
         commitment,
+        jackpotPool,
         player: player.publicKey,
+        playerState,
         systemProgram: SystemProgram.programId,
       } as any)
       .signers([player])
@@ -336,9 +892,14 @@ describe("slot_machine with Switchboard integration", () => {
       .revealAndSpin(new BN(secretValue), new BN(salt))
       .accounts({
         gameConfig,
+        paytable,
         treasury,
         commitment,
+        jackpotPool,
+        ...NO_TOKEN_ACCOUNTS,
+        spinResult: null,
         player: player.publicKey,
+        playerState,
         systemProgram: SystemProgram.programId,
       } as any)
       .signers([player])
@@ -350,30 +911,57 @@ describe("slot_machine with Switchboard integration", () => {
         .revealAndSpin(new BN(secretValue), new BN(salt))
         .accounts({
           gameConfig,
+          paytable,
           treasury,
           commitment,
+          jackpotPool,
+          ...NO_TOKEN_ACCOUNTS,
+          spinResult: null,
           player: player.publicKey,
+          playerState,
           systemProgram: SystemProgram.programId,
         } as any)
         .signers([player])
         .rpc();
       expect.fail("Should have thrown an error for double reveal");
     } catch (error) {
-      expect(error.message).to.include("AlreadyRevealed");
-      console.log("✅ Double reveal protection works");
+      expect(error.message).to.include("AccountNotInitialized");
     }
-  });
-
-  it("Prevents insufficient delay attacks", async () => {
-    const nonce = new BN(6);
-    const secretValue = crypto.randomInt(100000);
-    const salt = crypto.randomInt(100000);
-    const betAmount = new BN(10000000); // 0.01 SOL (minimum)
+
+    // The closed commitment's nonce cannot be committed again
+    try {
+      await program.methods
+        .commit(Array.from(commitmentHash), betAmount, nonce)
+        .accounts({
+          gameConfig,
+          randomnessClient,
+          paytable,
+          treasury,
+          commitment,
+          jackpotPool,
+          player: player.publicKey,
+          playerState,
+          systemProgram: SystemProgram.programId,
+        } as any)
+        .signers([player])
+        .rpc();
+      expect.fail("Should have thrown an error for a reused nonce");
+    } catch (error) {
+      expect(error.message).to.include("NonceAlreadyUsed");
+    }
+    console.log("✅ Double reveal protection works");
+  });
+
+  it("Prevents insufficient delay attacks", async () => {
+    const nonce = new BN(7);
+    const secretValue = crypto.randomInt(100000);
+    const salt = crypto.randomInt(100000);
+    const betAmount = new BN(10000000); // 0.01 SOL (minimum)
 
     const commitmentHash = createCommitmentHash(secretValue, salt, player.publicKey);
 
     const [commitment] = PublicKey.findProgramAddressSync(
-      [Buffer.from("commitment"), player.publicKey.toBuffer(), nonce.toArrayLike(Buffer, 'le', 8)],
+      [Buffer.from("commitment"), gameConfig.toBuffer(), player.publicKey.toBuffer(), nonce.toArrayLike(Buffer, 'le', 8)],
       program.programId
     );
 
@@ -381,9 +969,13 @@ describe("slot_machine with Switchboard integration", () => {
       .commit(Array.from(commitmentHash), betAmount, nonce)
       .accounts({
         gameConfig,
+        randomnessClient,
+        paytable,
         treasury,
         commitment,
+        jackpotPool,
         player: player.publicKey,
+        playerState,
         systemProgram: SystemProgram.programId,
       } as any)
       .signers([player])
@@ -395,9 +987,14 @@ describe("slot_machine with Switchboard integration", () => {
         .revealAndSpin(new BN(secretValue), new BN(salt))
         .accounts({
           gameConfig,
+          paytable,
           treasury,
           commitment,
+          jackpotPool,
+          ...NO_TOKEN_ACCOUNTS,
+          spinResult: null,
           player: player.publicKey,
+          playerState,
           systemProgram: SystemProgram.programId,
         } as any)
         .signers([player])
@@ -410,7 +1007,7 @@ describe("slot_machine with Switchboard integration", () => {
   });
 
   it("Prevents invalid reveals", async () => {
-    const nonce = new BN(7);
+    const nonce = new BN(8);
     const secretValue = crypto.randomInt(100000);
     const salt = crypto.randomInt(100000);
     const wrongSecret = crypto.randomInt(100000);
@@ -419,7 +1016,7 @@ describe("slot_machine with Switchboard integration", () => {
     const commitmentHash = createCommitmentHash(secretValue, salt, player.publicKey);
 
     const [commitment] = PublicKey.findProgramAddressSync(
-      [Buffer.from("commitment"), player.publicKey.toBuffer(), nonce.toArrayLike(Buffer, 'le', 8)],
+      [Buffer.from("commitment"), gameConfig.toBuffer(), player.publicKey.toBuffer(), nonce.toArrayLike(Buffer, 'le', 8)],
       program.programId
     );
 
@@ -427,9 +1024,13 @@ describe("slot_machine with Switchboard integration", () => {
       .commit(Array.from(commitmentHash), betAmount, nonce)
       .accounts({
         gameConfig,
+        randomnessClient,
+        paytable,
         treasury,
         commitment,
+        jackpotPool,
         player: player.publicKey,
+        playerState,
         systemProgram: SystemProgram.programId,
       } as any)
       .signers([player])
@@ -443,9 +1044,14 @@ describe("slot_machine with Switchboard integration", () => {
         .revealAndSpin(new BN(wrongSecret), new BN(salt))
         .accounts({
           gameConfig,
+          paytable,
           treasury,
           commitment,
+          jackpotPool,
+          ...NO_TOKEN_ACCOUNTS,
+          spinResult: null,
           player: player.publicKey,
+          playerState,
           systemProgram: SystemProgram.programId,
         } as any)
         .signers([player])
@@ -476,7 +1082,7 @@ describe("slot_machine with Switchboard integration", () => {
       const commitmentHash = createCommitmentHash(secretValue, salt, player.publicKey);
 
       const [commitment] = PublicKey.findProgramAddressSync(
-        [Buffer.from("commitment"), player.publicKey.toBuffer(), nonce.toArrayLike(Buffer, 'le', 8)],
+        [Buffer.from("commitment"), gameConfig.toBuffer(), player.publicKey.toBuffer(), nonce.toArrayLike(Buffer, 'le', 8)],
         program.programId
       );
 
@@ -484,9 +1090,13 @@ describe("slot_machine with Switchboard integration", () => {
         .commit(Array.from(commitmentHash), betAmount, nonce)
         .accounts({
           gameConfig,
+          randomnessClient,
+          paytable,
           treasury,
           commitment,
+          jackpotPool,
           player: player.publicKey,
+          playerState,
           systemProgram: SystemProgram.programId,
         } as any)
         .signers([player])
@@ -500,9 +1110,14 @@ describe("slot_machine with Switchboard integration", () => {
         .revealAndSpin(new BN(secretValue), new BN(salt))
         .accounts({
           gameConfig,
+          paytable,
           treasury,
           commitment,
+          jackpotPool,
+          ...NO_TOKEN_ACCOUNTS,
+          spinResult: null,
           player: player.publicKey,
+          playerState,
           systemProgram: SystemProgram.programId,
         } as any)
         .signers([player])
@@ -522,4 +1137,1948 @@ describe("slot_machine with Switchboard integration", () => {
     console.log(`\n📊 Results: ${wins}/${numTests} wins, Total payout: ${totalPayout / LAMPORTS_PER_SOL} SOL`);
     console.log("✅ Switchboard-ready randomness quality demonstrated");
   });
+
+  // The main machine has open bets by now, which lock its paytable, so the reels get a machine of their own
+  let reelGame: Awaited<ReturnType<typeof createMachine>>;
+
+  it("Rejects reel configurations above the RTP ceiling", async () => {
+    reelGame = await createMachine(new BN(3));
+
+    // Paying any cherry on the first reel pushes RTP to 131%
+    const generousPays = [...REEL_SYMBOL_PAYS, { symbol: 0, count: 1, multiplier: new BN(1), jackpot: false }];
+    try {
+      await program.methods
+        .configureReels(reelConfig(generousPays))
+        .accounts({
+          gameConfig: reelGame.machineConfig,
+          paytable: reelGame.machinePaytable,
+          authority: authority.publicKey,
+        } as any)
+        .signers([authority])
+        .rpc();
+      expect.fail("Should have thrown an error for reel RTP above the ceiling");
+    } catch (error) {
+      expect(error.message).to.include("RtpTooHigh");
+    }
+  });
+
+  it("Spins the reels and emits the resulting grid", async () => {
+    const { machineConfig, machineTreasury, machinePaytable, machinePlayerState } = reelGame;
+    const versionBefore = (await program.account.paytable.fetch(machinePaytable)).version;
+    await program.methods
+      .configureReels(reelConfig())
+      .accounts({
+        gameConfig: machineConfig,
+        paytable: machinePaytable,
+        authority: authority.publicKey,
+      } as any)
+      .signers([authority])
+      .rpc();
+
+    const paytableAccount = await program.account.paytable.fetch(machinePaytable);
+    expect(paytableAccount.version).to.equal(versionBefore + 1);
+    expect(paytableAccount.reels.reelStrips.length).to.equal(3);
+
+    const nonce = new BN(200);
+    const secretValue = crypto.randomInt(100000);
+    const salt = crypto.randomInt(100000);
+    const betAmount = new BN(10000000); // 0.01 SOL (minimum)
+
+    const commitmentHash = createCommitmentHash(secretValue, salt, player.publicKey);
+
+    const [commitment] = PublicKey.findProgramAddressSync(
+      [Buffer.from("commitment"), machineConfig.toBuffer(), player.publicKey.toBuffer(), nonce.toArrayLike(Buffer, 'le', 8)],
+      program.programId
+    );
+    const [spinResult] = PublicKey.findProgramAddressSync(
+      [SPIN_RESULT_SEED, machineConfig.toBuffer(), player.publicKey.toBuffer(), nonce.toArrayLike(Buffer, 'le', 8)],
+      program.programId
+    );
+
+    await program.methods
+      .commit(Array.from(commitmentHash), betAmount, nonce)
+      .accounts({
+        gameConfig: machineConfig,
+        randomnessClient,
+        paytable: machinePaytable,
+        treasury: machineTreasury,
+        commitment,
+        jackpotPool: null,
+        player: player.publicKey,
+        playerState: machinePlayerState,
+        systemProgram: SystemProgram.programId,
+      } as any)
+      .signers([player])
+      .rpc();
+
+    await sleep(3);
+
+    const signature = await program.methods
+      .revealAndSpin(new BN(secretValue), new BN(salt))
+      .accounts({
+        gameConfig: machineConfig,
+        paytable: machinePaytable,
+        treasury: machineTreasury,
+        commitment,
+        jackpotPool: null,
+        ...NO_TOKEN_ACCOUNTS,
+        spinResult,
+        player: player.publicKey,
+        playerState: machinePlayerState,
+        systemProgram: SystemProgram.programId,
+      } as any)
+      .signers([player])
+      .rpc({ commitment: "confirmed" });
+
+    const tx = await provider.connection.getTransaction(signature, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+    const parser = new anchor.EventParser(program.programId, program.coder);
+    const events = Array.from(parser.parseLogs(tx.meta.logMessages));
+    const reelsStopped = events.find((event) => event.name === "reelsStopped");
+    expect(reelsStopped).to.not.be.undefined;
+    expect(reelsStopped.data.rows).to.equal(REEL_ROWS);
+    expect(reelsStopped.data.reels).to.equal(3);
+    expect(reelsStopped.data.grid.length).to.equal(REEL_ROWS * 3);
+
+    // Every visible symbol must come from its reel strip
+    const grid: number[] = Array.from(reelsStopped.data.grid);
+    grid.forEach((symbol, index) => {
+      expect(REEL_STRIPS[index % 3]).to.include(symbol);
+    });
+    console.log(`🎰 Grid: ${JSON.stringify(grid)}, multiplier: ${reelsStopped.data.payoutMultiplier}`);
+
+    // The grid is also kept on the spin result, which outlives the transaction logs
+    const result = await program.account.spinResult.fetch(spinResult);
+    expect(Array.from(result.grid)).to.deep.equal(grid);
+    expect(result.rows).to.equal(REEL_ROWS);
+    expect(result.reels).to.equal(3);
+    await program.methods
+      .closeSpinResult()
+      .accounts({ spinResult, player: player.publicKey } as any)
+      .signers([player])
+      .rpc();
+
+    // Switch back to tier mode
+    await program.methods
+      .configureReels(TIER_MODE_REELS)
+      .accounts({
+        gameConfig: machineConfig,
+        paytable: machinePaytable,
+        authority: authority.publicKey,
+      } as any)
+      .signers([authority])
+      .rpc();
+    console.log("✅ Reel grid emitted for the UI");
+  });
+
+  it("Rejects free-spin bonuses worth a spin or more on average", async () => {
+    // Every visible scatter awarding 10 free spins makes the bonus self-sustaining
+    const strips = REEL_STRIPS.map((strip) => Buffer.from([...strip, SCATTER_SYMBOL]));
+    const config = {
+      ...reelConfig(REEL_SYMBOL_PAYS, [{ count: 1, multiplier: new BN(0), freeSpins: 10 }]),
+      reelStrips: strips,
+    };
+    try {
+      await program.methods
+        .configureReels(config)
+        .accounts({
+          gameConfig: reelGame.machineConfig,
+          paytable: reelGame.machinePaytable,
+          authority: authority.publicKey,
+        } as any)
+        .signers([authority])
+        .rpc();
+      expect.fail("Should have thrown an error for a self-sustaining free-spin bonus");
+    } catch (error) {
+      expect(error.message).to.include("InvalidReels");
+    }
+  });
+
+  it("Refuses free-spin commitments without free spins", async () => {
+    const nonce = new BN(300);
+    const commitmentHash = createCommitmentHash(crypto.randomInt(100000), crypto.randomInt(100000), player.publicKey);
+    const [commitment] = PublicKey.findProgramAddressSync(
+      [Buffer.from("commitment"), gameConfig.toBuffer(), player.publicKey.toBuffer(), nonce.toArrayLike(Buffer, 'le', 8)],
+      program.programId
+    );
+
+    const state = await program.account.playerState.fetch(playerState);
+    expect(state.freeSpins).to.equal(0);
+
+    try {
+      await program.methods
+        .commitFreeSpin(Array.from(commitmentHash), nonce)
+        .accounts({
+          gameConfig,
+          randomnessClient,
+          paytable,
+          treasury,
+          tokenVault: null,
+          playerState,
+          commitment,
+          player: player.publicKey,
+          systemProgram: SystemProgram.programId,
+        } as any)
+        .signers([player])
+        .rpc();
+      expect.fail("Should have thrown an error for missing free spins");
+    } catch (error) {
+      expect(error.message).to.include("NoFreeSpins");
+    }
+    console.log("✅ Free spins are required for free-spin commitments");
+  });
+
+  it("Feeds the jackpot pool from every bet", async () => {
+    const nonce = new BN(400);
+    const betAmount = new BN(100000000); // 0.1 SOL
+    const commitmentHash = createCommitmentHash(crypto.randomInt(100000), crypto.randomInt(100000), player.publicKey);
+    const [commitment] = PublicKey.findProgramAddressSync(
+      [Buffer.from("commitment"), gameConfig.toBuffer(), player.publicKey.toBuffer(), nonce.toArrayLike(Buffer, 'le', 8)],
+      program.programId
+    );
+
+    // Committing without the game's jackpot pool must fail
+    try {
+      await program.methods
+        .commit(Array.from(commitmentHash), betAmount, nonce)
+        .accounts({
+          gameConfig,
+          randomnessClient,
+          paytable,
+          treasury,
+          commitment,
+          jackpotPool: null,
+          player: player.publicKey,
+          playerState,
+          systemProgram: SystemProgram.programId,
+        } as any)
+        .signers([player])
+        .rpc();
+      expect.fail("Should have thrown an error for a missing jackpot pool");
+    } catch (error) {
+      expect(error.message).to.include("InvalidJackpotPool");
+    }
+
+    const poolBefore = await program.account.jackpotPool.fetch(jackpotPool);
+    const treasuryBefore = await provider.connection.getBalance(treasury);
+
+    await program.methods
+      .commit(Array.from(commitmentHash), betAmount, nonce)
+      .accounts({
+        gameConfig,
+        randomnessClient,
+        paytable,
+        treasury,
+        commitment,
+        jackpotPool,
+        player: player.publicKey,
+        playerState,
+        systemProgram: SystemProgram.programId,
+      } as any)
+      .signers([player])
+      .rpc();
+
+    const contribution = betAmount.muln(JACKPOT_CONTRIBUTION_BPS).divn(10_000);
+    const poolAfter = await program.account.jackpotPool.fetch(jackpotPool);
+    const treasuryAfter = await provider.connection.getBalance(treasury);
+    expect(poolAfter.balance.sub(poolBefore.balance).toString()).to.equal(contribution.toString());
+    expect(treasuryAfter - treasuryBefore).to.equal(betAmount.sub(contribution).toNumber());
+    console.log(`💰 Jackpot pool: ${poolAfter.balance.toNumber() / LAMPORTS_PER_SOL} SOL`);
+  });
+
+  it("Reserves worst-case payouts and refuses bets the treasury cannot cover", async () => {
+    const nonce = new BN(500);
+    const commitmentHash = createCommitmentHash(crypto.randomInt(100000), crypto.randomInt(100000), player.publicKey);
+    const [commitment] = PublicKey.findProgramAddressSync(
+      [Buffer.from("commitment"), gameConfig.toBuffer(), player.publicKey.toBuffer(), nonce.toArrayLike(Buffer, 'le', 8)],
+      program.programId
+    );
+    const accounts = {
+      gameConfig,
+      randomnessClient,
+      paytable,
+      treasury,
+      commitment,
+      jackpotPool,
+      player: player.publicKey,
+      playerState,
+      systemProgram: SystemProgram.programId,
+    } as any;
+
+    // 1 SOL at the top 25x tier is more than the treasury holds on top of the open commitments
+    try {
+      await program.methods
+        .commit(Array.from(commitmentHash), new BN(1000000000), nonce)
+        .accounts(accounts)
+        .signers([player])
+        .rpc();
+      expect.fail("Should have thrown an error for an uncovered bet");
+    } catch (error) {
+      expect(error.message).to.include("InsufficientTreasury");
+    }
+
+    const betAmount = new BN(10000000); // 0.01 SOL (minimum)
+    const configBefore = await program.account.gameConfig.fetch(gameConfig);
+    await program.methods
+      .commit(Array.from(commitmentHash), betAmount, nonce)
+      .accounts(accounts)
+      .signers([player])
+      .rpc();
+
+    const maxPayout = betAmount.muln(25);
+    const commitmentAccount = await program.account.commitment.fetch(commitment);
+    const configAfter = await program.account.gameConfig.fetch(gameConfig);
+    expect(commitmentAccount.maxPayout.toString()).to.equal(maxPayout.toString());
+    expect(configAfter.outstandingLiability.sub(configBefore.outstandingLiability).toString()).to.equal(maxPayout.toString());
+    console.log(`✅ Outstanding liability: ${configAfter.outstandingLiability.toNumber() / LAMPORTS_PER_SOL} SOL`);
+  });
+
+  it("Expires abandoned commitments per the expiry policy", async () => {
+    const secretValue = crypto.randomInt(100000);
+    const salt = crypto.randomInt(100000);
+    const betAmount = new BN(10000000); // 0.01 SOL (minimum)
+    const commitmentHash = createCommitmentHash(secretValue, salt, player.publicKey);
+    const commitAt = async (nonce: BN) => {
+      const [commitment] = PublicKey.findProgramAddressSync(
+        [Buffer.from("commitment"), gameConfig.toBuffer(), player.publicKey.toBuffer(), nonce.toArrayLike(Buffer, 'le', 8)],
+        program.programId
+      );
+      await program.methods
+        .commit(Array.from(commitmentHash), betAmount, nonce)
+        .accounts({
+          gameConfig,
+          randomnessClient,
+          paytable,
+          treasury,
+          commitment,
+          jackpotPool,
+          player: player.publicKey,
+          playerState,
+          systemProgram: SystemProgram.programId,
+        } as any)
+        .signers([player])
+        .rpc();
+      return commitment;
+    };
+    const setSwitchboard = (enabled: boolean) =>
+      program.methods
+        .setUseSwitchboard(enabled)
+        .accounts({ authority: authority.publicKey, randomnessClient } as any)
+        .signers([authority])
+        .rpc();
+    const expireAccounts = (commitment: PublicKey) => ({
+      gameConfig,
+      treasury,
+      commitment,
+      ...NO_TOKEN_ACCOUNTS,
+      player: player.publicKey,
+      systemProgram: SystemProgram.programId,
+    } as any);
+
+    // Only the authority sets the expiry policy
+    try {
+      await program.methods
+        .setExpiryPolicy(new BN(8), { refund: {} })
+        .accounts({ gameConfig, authority: player.publicKey } as any)
+        .signers([player])
+        .rpc();
+      expect.fail("Should have thrown an error for non-authority");
+    } catch (error) {
+      expect(error.message).to.include("Unauthorized");
+    }
+
+    await program.methods
+      .setExpiryPolicy(new BN(8), { refund: {} })
+      .accounts({ gameConfig, authority: authority.publicKey } as any)
+      .signers([authority])
+      .rpc();
+
+    // A fallback bet, and an oracle bet whose randomness is never requested
+    const fallbackCommitment = await commitAt(new BN(600));
+    await setSwitchboard(true);
+    const oracleCommitment = await commitAt(new BN(601));
+    await setSwitchboard(false);
+
+    try {
+      await program.methods.expireCommitment().accounts(expireAccounts(fallbackCommitment)).rpc();
+      expect.fail("Should have thrown an error before the deadline");
+    } catch (error) {
+      expect(error.message).to.include("CommitmentNotExpired");
+    }
+
+    await sleep(4);
+
+    // Past the deadline the commitment can no longer be revealed
+    try {
+      await program.methods
+        .revealAndSpin(new BN(secretValue), new BN(salt))
+        .accounts({
+          gameConfig,
+          paytable,
+          treasury,
+          commitment: fallbackCommitment,
+          jackpotPool,
+          ...NO_TOKEN_ACCOUNTS,
+          spinResult: null,
+          player: player.publicKey,
+          playerState,
+          systemProgram: SystemProgram.programId,
+        } as any)
+        .signers([player])
+        .rpc();
+      expect.fail("Should have thrown an error for an expired commitment");
+    } catch (error) {
+      expect(error.message).to.include("CommitmentExpired");
+    }
+
+    // Anyone can expire them; the rent always goes back to the player
+    const expire = async (commitment: PublicKey) => {
+      const rent = (await provider.connection.getAccountInfo(commitment)).lamports;
+      const configBefore = await program.account.gameConfig.fetch(gameConfig);
+      const balanceBefore = await provider.connection.getBalance(player.publicKey);
+      await program.methods.expireCommitment().accounts(expireAccounts(commitment)).rpc();
+      const configAfter = await program.account.gameConfig.fetch(gameConfig);
+      expect(await provider.connection.getAccountInfo(commitment)).to.be.null;
+      expect(configBefore.outstandingLiability.sub(configAfter.outstandingLiability).toString()).to.equal(betAmount.muln(25).toString());
+      return (await provider.connection.getBalance(player.publicKey)) - balanceBefore - rent;
+    };
+
+    // The fallback outcome was computable once its entropy slot passed, so Refund does not
+    // apply and the bet stays with the treasury
+    expect(await expire(fallbackCommitment)).to.equal(0);
+    // Nobody could know the unrequested oracle outcome, so that bet is refunded
+    expect(await expire(oracleCommitment)).to.equal(betAmount.toNumber());
+
+    // Restore the default deadline so later commitments are unaffected
+    await program.methods
+      .setExpiryPolicy(new BN(216_000), { forfeit: {} })
+      .accounts({ gameConfig, authority: authority.publicKey } as any)
+      .signers([authority])
+      .rpc();
+    console.log("✅ Expired commitments closed, refunded only while their outcome was unknown");
+  });
+
+  it("Plays a separate game with SPL token bets", async () => {
+    // A second operator runs a token game next to the SOL game
+    const {
+      tokenAuthority,
+      tokenGameConfig,
+      tokenTreasury,
+      tokenRandomnessClient,
+      tokenPaytable,
+      tokenPlayerState,
+      tokenVault,
+      mint,
+      playerTokenAccount,
+    } = await createTokenGame();
+
+    // Progressive jackpots are paid in SOL, so token games cannot have one
+    const [tokenJackpotPool] = PublicKey.findProgramAddressSync([JACKPOT_SEED, tokenGameConfig.toBuffer()], program.programId);
+    try {
+      await program.methods
+        .createJackpotPool(JACKPOT_CONTRIBUTION_BPS, JACKPOT_RESEED)
+        .accounts({
+          gameConfig: tokenGameConfig,
+          jackpotPool: tokenJackpotPool,
+          authority: tokenAuthority.publicKey,
+          systemProgram: SystemProgram.programId,
+        } as any)
+        .signers([tokenAuthority])
+        .rpc();
+      expect.fail("Should have thrown an error for a token game jackpot");
+    } catch (error) {
+      expect(error.message).to.include("JackpotRequiresSol");
+    }
+
+    const nonce = new BN(700);
+    const secretValue = crypto.randomInt(100000);
+    const salt = crypto.randomInt(100000);
+    const betAmount = new BN(10_000_000); // 10 tokens
+    const commitmentHash = createCommitmentHash(secretValue, salt, player.publicKey);
+    const [commitment] = PublicKey.findProgramAddressSync(
+      [Buffer.from("commitment"), tokenGameConfig.toBuffer(), player.publicKey.toBuffer(), nonce.toArrayLike(Buffer, 'le', 8)],
+      program.programId
+    );
+
+    // SOL bets are refused on a token game
+    try {
+      await program.methods
+        .commit(Array.from(commitmentHash), betAmount, nonce)
+        .accounts({
+          gameConfig: tokenGameConfig,
+          randomnessClient: tokenRandomnessClient,
+          paytable: tokenPaytable,
+          treasury: tokenTreasury,
+          commitment,
+          jackpotPool: null,
+          player: player.publicKey,
+          playerState: tokenPlayerState,
+          systemProgram: SystemProgram.programId,
+        } as any)
+        .signers([player])
+        .rpc();
+      expect.fail("Should have thrown an error for a SOL bet");
+    } catch (error) {
+      expect(error.message).to.include("WrongBetCurrency");
+    }
+
+    const vaultBefore = await tokenBalance(tokenVault);
+    await program.methods
+      .commitToken(Array.from(commitmentHash), betAmount, nonce)
+      .accounts({
+        gameConfig: tokenGameConfig,
+        randomnessClient: tokenRandomnessClient,
+        paytable: tokenPaytable,
+        betMint: mint,
+        tokenVault,
+        playerTokenAccount,
+        commitment,
+        playerState: tokenPlayerState,
+        player: player.publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      } as any)
+      .signers([player])
+      .rpc();
+
+    const commitmentAccount = await program.account.commitment.fetch(commitment);
+    expect(commitmentAccount.betMint.toString()).to.equal(mint.toString());
+    expect((await tokenBalance(tokenVault)).sub(vaultBefore).toString()).to.equal(betAmount.toString());
+
+    await sleep(3);
+
+    const playerBefore = await tokenBalance(playerTokenAccount);
+    const vaultAfterCommit = await tokenBalance(tokenVault);
+    await program.methods
+      .revealAndSpin(new BN(secretValue), new BN(salt))
+      .accounts({
+        gameConfig: tokenGameConfig,
+        paytable: tokenPaytable,
+        treasury: tokenTreasury,
+        commitment,
+        jackpotPool: null,
+        betMint: mint,
+        tokenVault,
+        playerTokenAccount,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        spinResult: null,
+        player: player.publicKey,
+        playerState: tokenPlayerState,
+        systemProgram: SystemProgram.programId,
+      } as any)
+      .signers([player])
+      .rpc();
+
+    // Any payout moves from the vault to the player's token account
+    const payout = (await tokenBalance(playerTokenAccount)).sub(playerBefore);
+    expect(vaultAfterCommit.sub(await tokenBalance(tokenVault)).toString()).to.equal(payout.toString());
+    expect(await provider.connection.getAccountInfo(commitment)).to.be.null;
+    console.log(`✅ Token spin settled. Payout: ${payout.toString()} base units`);
+  });
+
+  it("Refuses token betting while the SOL treasury holds funds", async () => {
+    // Token games only pay and withdraw from the vault, so this SOL would be stranded
+    const { machineConfig, machineTreasury } = await createMachine(new BN(6));
+    const mint = await createMint(authority, 6);
+    const [tokenVault] = PublicKey.findProgramAddressSync([TOKEN_VAULT_SEED, machineConfig.toBuffer()], program.programId);
+    try {
+      await program.methods
+        .enableTokenBetting()
+        .accounts({
+          gameConfig: machineConfig,
+          betMint: mint,
+          treasury: machineTreasury,
+          tokenVault,
+          authority: authority.publicKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+        } as any)
+        .signers([authority])
+        .rpc();
+      expect.fail("Should have thrown an error for a funded SOL treasury");
+    } catch (error) {
+      expect(error.message).to.include("TreasuryNotEmpty");
+    }
+    console.log("✅ SOL bankroll must be withdrawn before switching to token bets");
+  });
+
+  it("Records the net bet received from Token-2022 transfer-fee mints", async () => {
+    const transferFeeBps = 100; // 1% withheld on every transfer
+    const { tokenGameConfig, tokenRandomnessClient, tokenPaytable, tokenPlayerState, tokenVault, mint, playerTokenAccount } =
+      await createTokenGame(TOKEN_2022_PROGRAM_ID, transferFeeBps);
+
+    const nonce = new BN(800);
+    const sent = new BN(20_000_000);
+    const commitmentHash = createCommitmentHash(crypto.randomInt(100000), crypto.randomInt(100000), player.publicKey);
+    const [commitment] = PublicKey.findProgramAddressSync(
+      [Buffer.from("commitment"), tokenGameConfig.toBuffer(), player.publicKey.toBuffer(), nonce.toArrayLike(Buffer, 'le', 8)],
+      program.programId
+    );
+
+    const vaultBefore = await tokenBalance(tokenVault);
+    const configBefore = await program.account.gameConfig.fetch(tokenGameConfig);
+    await program.methods
+      .commitToken(Array.from(commitmentHash), sent, nonce)
+      .accounts({
+        gameConfig: tokenGameConfig,
+        randomnessClient: tokenRandomnessClient,
+        paytable: tokenPaytable,
+        betMint: mint,
+        tokenVault,
+        playerTokenAccount,
+        commitment,
+        playerState: tokenPlayerState,
+        player: player.publicKey,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      } as any)
+      .signers([player])
+      .rpc();
+
+    // The bet and its reserved payout are based on what the vault actually received
+    const received = sent.sub(sent.muln(transferFeeBps).divn(10_000));
+    const commitmentAccount = await program.account.commitment.fetch(commitment);
+    const configAfter = await program.account.gameConfig.fetch(tokenGameConfig);
+    expect((await tokenBalance(tokenVault)).sub(vaultBefore).toString()).to.equal(received.toString());
+    expect(commitmentAccount.betAmount.toString()).to.equal(received.toString());
+    expect(configAfter.outstandingLiability.sub(configBefore.outstandingLiability).toString()).to.equal(received.muln(25).toString());
+    console.log(`✅ Token-2022 bet recorded net of fees: ${received.toString()} of ${sent.toString()}`);
+  });
+
+  it("Runs independent machines with separate treasuries", async () => {
+    // The same authority opens a second machine
+    const machineId = new BN(1);
+    const secondGameConfig = findGameConfig(program.programId, authority.publicKey, machineId);
+    const [secondTreasury] = findTreasury(program.programId, secondGameConfig);
+    const [secondPaytable] = PublicKey.findProgramAddressSync([PAYTABLE_SEED, secondGameConfig.toBuffer()], program.programId);
+    const [secondPlayerState] = PublicKey.findProgramAddressSync(
+      [PLAYER_STATE_SEED, secondGameConfig.toBuffer(), player.publicKey.toBuffer()],
+      program.programId
+    );
+    expect(secondTreasury.toBase58()).to.not.equal(treasury.toBase58());
+
+    await program.methods.initialize(machineId).accounts({
+      gameConfig: secondGameConfig,
+      treasury: secondTreasury,
+      authority: authority.publicKey,
+      systemProgram: SystemProgram.programId,
+    } as any).signers([authority]).rpc();
+    await program.methods
+      .createPaytable(PAYOUT_TIERS, ODDS_DENOMINATOR, MAX_RTP_BPS)
+      .accounts({
+        gameConfig: secondGameConfig,
+        paytable: secondPaytable,
+        authority: authority.publicKey,
+        systemProgram: SystemProgram.programId,
+      } as any)
+      .signers([authority])
+      .rpc();
+    await airdrop(secondTreasury, 5);
+
+    const nonce = new BN(900);
+    const betAmount = new BN(10000000); // 0.01 SOL (minimum)
+    const commitmentHash = createCommitmentHash(crypto.randomInt(100000), crypto.randomInt(100000), player.publicKey);
+    const [commitment] = PublicKey.findProgramAddressSync(
+      [Buffer.from("commitment"), secondGameConfig.toBuffer(), player.publicKey.toBuffer(), nonce.toArrayLike(Buffer, 'le', 8)],
+      program.programId
+    );
+    const accounts = {
+      gameConfig: secondGameConfig,
+      randomnessClient,
+      paytable: secondPaytable,
+      treasury: secondTreasury,
+      commitment,
+      jackpotPool: null,
+      player: player.publicKey,
+      playerState: secondPlayerState,
+      systemProgram: SystemProgram.programId,
+    };
+
+    // Another machine's treasury cannot stand in for this machine's
+    try {
+      await program.methods
+        .commit(Array.from(commitmentHash), betAmount, nonce)
+        .accounts({ ...accounts, treasury } as any)
+        .signers([player])
+        .rpc();
+      expect.fail("Should have thrown an error for a foreign treasury");
+    } catch (error) {
+      expect(error.message).to.include("ConstraintSeeds");
+    }
+
+    const firstBefore = await provider.connection.getBalance(treasury);
+    const secondBefore = await provider.connection.getBalance(secondTreasury);
+    await program.methods
+      .commit(Array.from(commitmentHash), betAmount, nonce)
+      .accounts(accounts as any)
+      .signers([player])
+      .rpc();
+
+    expect(await provider.connection.getBalance(treasury)).to.equal(firstBefore);
+    expect((await provider.connection.getBalance(secondTreasury)) - secondBefore).to.equal(betAmount.toNumber());
+
+    // Commitment addresses are scoped to the machine, so the same nonce stays free on the first one
+    const [firstMachineCommitment] = PublicKey.findProgramAddressSync(
+      [Buffer.from("commitment"), gameConfig.toBuffer(), player.publicKey.toBuffer(), nonce.toArrayLike(Buffer, 'le', 8)],
+      program.programId
+    );
+    expect(firstMachineCommitment.toBase58()).to.not.equal(commitment.toBase58());
+
+    // The commitment is bound to its machine and cannot be settled against another one
+    try {
+      await program.methods
+        .revealAndSpin(new BN(0), new BN(0))
+        .accounts({
+          gameConfig,
+          paytable,
+          treasury,
+          commitment,
+          jackpotPool,
+          ...NO_TOKEN_ACCOUNTS,
+          spinResult: null,
+          player: player.publicKey,
+          playerState,
+          systemProgram: SystemProgram.programId,
+        } as any)
+        .signers([player])
+        .rpc();
+      expect.fail("Should have thrown an error for a foreign game config");
+    } catch (error) {
+      expect(error.message).to.include("ConstraintSeeds");
+    }
+    console.log("✅ Machines keep their bets in separate treasuries");
+  });
+
+  it("Locks the paytable while commitments are open", async () => {
+    const { machineConfig, machineTreasury, machinePaytable, machinePlayerState } = await createMachine(new BN(4));
+    const nonce = new BN(1000);
+    const secretValue = crypto.randomInt(100000);
+    const salt = crypto.randomInt(100000);
+    const betAmount = new BN(10000000); // 0.01 SOL (minimum)
+    const [commitment] = PublicKey.findProgramAddressSync(
+      [Buffer.from("commitment"), machineConfig.toBuffer(), player.publicKey.toBuffer(), nonce.toArrayLike(Buffer, 'le', 8)],
+      program.programId
+    );
+    const updatePaytable = () =>
+      program.methods
+        .updatePaytable(PAYOUT_TIERS, ODDS_DENOMINATOR, MAX_RTP_BPS)
+        .accounts({ gameConfig: machineConfig, paytable: machinePaytable, authority: authority.publicKey } as any)
+        .signers([authority])
+        .rpc();
+
+    await program.methods
+      .commit(Array.from(createCommitmentHash(secretValue, salt, player.publicKey)), betAmount, nonce)
+      .accounts({
+        gameConfig: machineConfig,
+        randomnessClient,
+        paytable: machinePaytable,
+        treasury: machineTreasury,
+        commitment,
+        jackpotPool: null,
+        player: player.publicKey,
+        playerState: machinePlayerState,
+        systemProgram: SystemProgram.programId,
+      } as any)
+      .signers([player])
+      .rpc();
+    const versionBefore = (await program.account.paytable.fetch(machinePaytable)).version;
+
+    // The operator cannot change the odds under an open bet, neither the tiers nor the reels
+    try {
+      await updatePaytable();
+      expect.fail("Should have thrown an error for a paytable update with open commitments");
+    } catch (error) {
+      expect(error.message).to.include("PaytableLocked");
+    }
+    try {
+      await program.methods
+        .configureReels(reelConfig())
+        .accounts({ gameConfig: machineConfig, paytable: machinePaytable, authority: authority.publicKey } as any)
+        .signers([authority])
+        .rpc();
+      expect.fail("Should have thrown an error for a reel change with open commitments");
+    } catch (error) {
+      expect(error.message).to.include("PaytableLocked");
+    }
+
+    // The bet settles at the odds it was placed at, which unlocks the paytable
+    await sleep(3);
+    await program.methods
+      .revealAndSpin(new BN(secretValue), new BN(salt))
+      .accounts({
+        gameConfig: machineConfig,
+        paytable: machinePaytable,
+        treasury: machineTreasury,
+        commitment,
+        jackpotPool: null,
+        ...NO_TOKEN_ACCOUNTS,
+        spinResult: null,
+        player: player.publicKey,
+        playerState: machinePlayerState,
+        systemProgram: SystemProgram.programId,
+      } as any)
+      .signers([player])
+      .rpc();
+    expect((await program.account.gameConfig.fetch(machineConfig)).outstandingLiability.toNumber()).to.equal(0);
+
+    await updatePaytable();
+    expect((await program.account.paytable.fetch(machinePaytable)).version).to.equal(versionBefore + 1);
+    console.log("✅ Paytable locked until open commitments settle");
+  });
+
+  it("Updates bet limits and reveal delay through update_config", async () => {
+    const config = await program.account.gameConfig.fetch(gameConfig);
+    expect(config.minBetAmount.toString()).to.equal("10000000");
+    expect(config.maxBetAmount.toString()).to.equal("1000000000");
+    expect(config.minDelaySlots.toNumber()).to.equal(4);
+
+    const update = (minBet: BN, maxBet: BN, delay: number, signer: Keypair) =>
+      program.methods
+        .updateConfig(minBet, maxBet, new BN(delay))
+        .accounts({ gameConfig, authority: signer.publicKey } as any)
+        .signers([signer])
+        .rpc();
+
+    try {
+      await update(new BN(20000000), new BN(1000000000), 4, player);
+      expect.fail("Should have thrown an error for non-authority");
+    } catch (error) {
+      expect(error.message).to.include("Unauthorized");
+    }
+    try {
+      await update(new BN(2000000000), new BN(1000000000), 4, authority);
+      expect.fail("Should have thrown an error for inverted bet limits");
+    } catch (error) {
+      expect(error.message).to.include("InvalidConfig");
+    }
+    try {
+      await update(new BN(20000000), new BN(1000000000), 0, authority);
+      expect.fail("Should have thrown an error for a zero reveal delay");
+    } catch (error) {
+      expect(error.message).to.include("InvalidConfig");
+    }
+
+    // Raise the minimum bet; the old minimum is refused afterwards
+    await update(new BN(20000000), new BN(1000000000), 6, authority);
+    const nonce = new BN(1100);
+    const commitmentHash = createCommitmentHash(crypto.randomInt(100000), crypto.randomInt(100000), player.publicKey);
+    const [commitment] = PublicKey.findProgramAddressSync(
+      [Buffer.from("commitment"), gameConfig.toBuffer(), player.publicKey.toBuffer(), nonce.toArrayLike(Buffer, 'le', 8)],
+      program.programId
+    );
+    try {
+      await program.methods
+        .commit(Array.from(commitmentHash), new BN(10000000), nonce)
+        .accounts({
+          gameConfig,
+          randomnessClient,
+          paytable,
+          treasury,
+          commitment,
+          jackpotPool,
+          player: player.publicKey,
+          playerState,
+          systemProgram: SystemProgram.programId,
+        } as any)
+        .signers([player])
+        .rpc();
+      expect.fail("Should have thrown an error for a bet below the new minimum");
+    } catch (error) {
+      expect(error.message).to.include("BetTooLow");
+    }
+
+    const updated = await program.account.gameConfig.fetch(gameConfig);
+    expect(updated.minDelaySlots.toNumber()).to.equal(6);
+
+    // Restore the defaults for any later tests
+    await update(new BN(10000000), new BN(1000000000), 4, authority);
+    console.log("✅ Config limits updated by the authority only");
+  });
+
+  it("Pauses new bets while letting open commitments settle", async () => {
+    const guardian = Keypair.generate();
+    const commitAt = (nonce: BN, hash: Buffer) => {
+      const [commitment] = PublicKey.findProgramAddressSync(
+        [Buffer.from("commitment"), gameConfig.toBuffer(), player.publicKey.toBuffer(), nonce.toArrayLike(Buffer, 'le', 8)],
+        program.programId
+      );
+      return {
+        commitment,
+        rpc: () =>
+          program.methods
+            .commit(Array.from(hash), new BN(10000000), nonce)
+            .accounts({
+              gameConfig,
+              randomnessClient,
+              paytable,
+              treasury,
+              commitment,
+              jackpotPool,
+              player: player.publicKey,
+              playerState,
+              systemProgram: SystemProgram.programId,
+            } as any)
+            .signers([player])
+            .rpc(),
+      };
+    };
+
+    try {
+      await program.methods
+        .pause()
+        .accounts({ gameConfig, signer: guardian.publicKey } as any)
+        .signers([guardian])
+        .rpc();
+      expect.fail("Should have thrown an error before the guardian is set");
+    } catch (error) {
+      expect(error.message).to.include("Unauthorized");
+    }
+
+    await program.methods
+      .setGuardian(guardian.publicKey)
+      .accounts({ gameConfig, authority: authority.publicKey } as any)
+      .signers([authority])
+      .rpc();
+
+    // Open a commitment, then pause before it is revealed
+    const secretValue = crypto.randomInt(100000);
+    const salt = crypto.randomInt(100000);
+    const open = commitAt(new BN(1200), createCommitmentHash(secretValue, salt, player.publicKey));
+    await open.rpc();
+
+    await program.methods
+      .pause()
+      .accounts({ gameConfig, signer: guardian.publicKey } as any)
+      .signers([guardian])
+      .rpc();
+    expect((await program.account.gameConfig.fetch(gameConfig)).paused).to.be.true;
+
+    try {
+      await commitAt(new BN(1201), createCommitmentHash(1, 2, player.publicKey)).rpc();
+      expect.fail("Should have thrown an error while paused");
+    } catch (error) {
+      expect(error.message).to.include("MachinePaused");
+    }
+
+    // The open commitment still settles so the player's bet is not trapped
+    await sleep(3);
+    await program.methods
+      .revealAndSpin(new BN(secretValue), new BN(salt))
+      .accounts({
+        gameConfig,
+        paytable,
+        treasury,
+        commitment: open.commitment,
+        jackpotPool,
+        ...NO_TOKEN_ACCOUNTS,
+        spinResult: null,
+        player: player.publicKey,
+        playerState,
+        systemProgram: SystemProgram.programId,
+      } as any)
+      .signers([player])
+      .rpc();
+    expect(await provider.connection.getAccountInfo(open.commitment)).to.be.null;
+
+    await program.methods
+      .unpause()
+      .accounts({ gameConfig, signer: authority.publicKey } as any)
+      .signers([authority])
+      .rpc();
+    await commitAt(new BN(1202), createCommitmentHash(3, 4, player.publicKey)).rpc();
+    console.log("✅ Paused machine refuses bets but settles open commitments");
+  });
+
+  it("Hands over authority in two steps", async () => {
+    const newAuthority = Keypair.generate();
+
+    try {
+      await program.methods
+        .proposeAuthority(newAuthority.publicKey)
+        .accounts({ gameConfig, authority: player.publicKey } as any)
+        .signers([player])
+        .rpc();
+      expect.fail("Should have thrown an error for non-authority proposal");
+    } catch (error) {
+      expect(error.message).to.include("Unauthorized");
+    }
+
+    await program.methods
+      .proposeAuthority(newAuthority.publicKey)
+      .accounts({ gameConfig, authority: authority.publicKey } as any)
+      .signers([authority])
+      .rpc();
+    // Proposing alone changes nothing
+    let config = await program.account.gameConfig.fetch(gameConfig);
+    expect(config.authority.toString()).to.equal(authority.publicKey.toString());
+    expect(config.pendingAuthority.toString()).to.equal(newAuthority.publicKey.toString());
+
+    try {
+      await program.methods
+        .acceptAuthority()
+        .accounts({ gameConfig, newAuthority: player.publicKey } as any)
+        .signers([player])
+        .rpc();
+      expect.fail("Should have thrown an error for accept by a non-pending key");
+    } catch (error) {
+      expect(error.message).to.include("Unauthorized");
+    }
+
+    await program.methods
+      .acceptAuthority()
+      .accounts({ gameConfig, newAuthority: newAuthority.publicKey } as any)
+      .signers([newAuthority])
+      .rpc();
+    config = await program.account.gameConfig.fetch(gameConfig);
+    expect(config.authority.toString()).to.equal(newAuthority.publicKey.toString());
+    expect(config.pendingAuthority.toString()).to.equal(PublicKey.default.toString());
+    // The game keeps the randomness client it was created with
+    expect(config.randomnessClient.toString()).to.equal(randomnessClient.toString());
+
+    try {
+      await program.methods
+        .setGuardian(PublicKey.default)
+        .accounts({ gameConfig, authority: authority.publicKey } as any)
+        .signers([authority])
+        .rpc();
+      expect.fail("Should have thrown an error for the previous authority");
+    } catch (error) {
+      expect(error.message).to.include("Unauthorized");
+    }
+
+    // The randomness client is handed over the same way
+    await program.methods
+      .proposeRandomnessAuthority(newAuthority.publicKey)
+      .accounts({ randomnessClient, authority: authority.publicKey } as any)
+      .signers([authority])
+      .rpc();
+    await program.methods
+      .acceptRandomnessAuthority()
+      .accounts({ randomnessClient, newAuthority: newAuthority.publicKey } as any)
+      .signers([newAuthority])
+      .rpc();
+    const client = await program.account.randomnessClient.fetch(randomnessClient);
+    expect(client.authority.toString()).to.equal(newAuthority.publicKey.toString());
+
+    // Hand both back for any later tests
+    await program.methods
+      .proposeAuthority(authority.publicKey)
+      .accounts({ gameConfig, authority: newAuthority.publicKey } as any)
+      .signers([newAuthority])
+      .rpc();
+    await program.methods
+      .acceptAuthority()
+      .accounts({ gameConfig, newAuthority: authority.publicKey } as any)
+      .signers([authority])
+      .rpc();
+    await program.methods
+      .proposeRandomnessAuthority(authority.publicKey)
+      .accounts({ randomnessClient, authority: newAuthority.publicKey } as any)
+      .signers([newAuthority])
+      .rpc();
+    await program.methods
+      .acceptRandomnessAuthority()
+      .accounts({ randomnessClient, newAuthority: authority.publicKey } as any)
+      .signers([authority])
+      .rpc();
+    console.log("✅ Authority transferred only after the new key accepts");
+  });
+
+  it("Deposits and withdraws treasury funds above the reserve", async () => {
+    const withdraw = (signer: Keypair) =>
+      program.methods
+        .withdrawTreasury()
+        .accounts({
+          gameConfig,
+          treasury,
+          betMint: null,
+          tokenVault: null,
+          authorityTokenAccount: null,
+          tokenProgram: null,
+          authority: signer.publicKey,
+          systemProgram: SystemProgram.programId,
+        } as any)
+        .signers([signer])
+        .rpc();
+    const requestWithdrawal = (amount: BN) =>
+      program.methods
+        .requestWithdrawal(amount)
+        .accounts({ gameConfig, authority: authority.publicKey } as any)
+        .signers([authority])
+        .rpc({ commitment: "confirmed" });
+    const setTimelock = (seconds: number) =>
+      program.methods
+        .setWithdrawalTimelock(new BN(seconds))
+        .accounts({ gameConfig, authority: authority.publicKey } as any)
+        .signers([authority])
+        .rpc();
+
+    const depositAmount = 1 * LAMPORTS_PER_SOL;
+    const treasuryBefore = await provider.connection.getBalance(treasury);
+    await program.methods
+      .depositTreasury(new BN(depositAmount))
+      .accounts({
+        gameConfig,
+        treasury,
+        betMint: null,
+        tokenVault: null,
+        depositorTokenAccount: null,
+        tokenProgram: null,
+        depositor: authority.publicKey,
+        systemProgram: SystemProgram.programId,
+      } as any)
+      .signers([authority])
+      .rpc();
+    expect(await provider.connection.getBalance(treasury)).to.equal(treasuryBefore + depositAmount);
+
+    try {
+      await withdraw(authority);
+      expect.fail("Should have thrown an error without a pending withdrawal");
+    } catch (error) {
+      expect(error.message).to.include("NoPendingWithdrawal");
+    }
+
+    // A time-locked request cannot be executed early, and is announced with its unlock time
+    await setTimelock(3);
+    const requestSignature = await requestWithdrawal(new BN(depositAmount / 2));
+    const requested = (await eventsOf(requestSignature)).find((event) => event.name === "withdrawalRequested");
+    expect(requested).to.not.be.undefined;
+    expect(requested.data.amount.toNumber()).to.equal(depositAmount / 2);
+    const locked = await program.account.gameConfig.fetch(gameConfig);
+    expect(requested.data.unlockAt.toNumber()).to.equal(locked.withdrawalUnlockAt.toNumber());
+    try {
+      await withdraw(authority);
+      expect.fail("Should have thrown an error for a time-locked withdrawal");
+    } catch (error) {
+      expect(error.message).to.include("WithdrawalLocked");
+    }
+    try {
+      await withdraw(player);
+      expect.fail("Should have thrown an error for non-authority withdrawal");
+    } catch (error) {
+      expect(error.message).to.include("Unauthorized");
+    }
+
+    // Lowering the timelock is itself time-locked, so it can't be dropped to withdraw at once
+    await setTimelock(0);
+    const queued = await program.account.gameConfig.fetch(gameConfig);
+    expect(queued.withdrawalTimelockSeconds.toNumber()).to.equal(3);
+    expect(queued.pendingWithdrawalTimelockSeconds.toNumber()).to.equal(0);
+    expect(queued.withdrawalTimelockChangeAt.toNumber()).to.be.greaterThan(0);
+    await requestWithdrawal(new BN(depositAmount / 2));
+    try {
+      await withdraw(authority);
+      expect.fail("Should have thrown an error for a re-requested withdrawal under the old timelock");
+    } catch (error) {
+      expect(error.message).to.include("WithdrawalLocked");
+    }
+
+    // Rent and the liability reserved for open commitments can't be withdrawn
+    await sleep(4);
+    const config = await program.account.gameConfig.fetch(gameConfig);
+    const rent = await provider.connection.getMinimumBalanceForRentExemption(0);
+    const withdrawable = (await provider.connection.getBalance(treasury)) - rent - config.outstandingLiability.toNumber();
+    await requestWithdrawal(new BN(withdrawable + 1));
+    try {
+      await withdraw(authority);
+      expect.fail("Should have thrown an error for withdrawing reserved funds");
+    } catch (error) {
+      expect(error.message).to.include("InsufficientTreasury");
+    }
+
+    await requestWithdrawal(new BN(depositAmount / 2));
+    const beforeWithdraw = await provider.connection.getBalance(treasury);
+    await withdraw(authority);
+    expect(await provider.connection.getBalance(treasury)).to.equal(beforeWithdraw - depositAmount / 2);
+    const cleared = await program.account.gameConfig.fetch(gameConfig);
+    expect(cleared.pendingWithdrawalAmount.toNumber()).to.equal(0);
+    expect(cleared.withdrawalTimelockSeconds.toNumber()).to.equal(0);
+    expect(cleared.withdrawalTimelockChangeAt.toNumber()).to.equal(0);
+    console.log("✅ Treasury withdrawals respect the reserve and timelock");
+  });
+
+  it("Pools LP liquidity with shares that accrue house profit", async () => {
+    // A fresh machine that already holds some operator bankroll
+    const machineId = new BN(2);
+    const poolGameConfig = findGameConfig(program.programId, authority.publicKey, machineId);
+    const [poolTreasury] = findTreasury(program.programId, poolGameConfig);
+    const [lpMint] = PublicKey.findProgramAddressSync([LP_MINT_SEED, poolGameConfig.toBuffer()], program.programId);
+    await program.methods.initialize(machineId).accounts({
+      gameConfig: poolGameConfig,
+      treasury: poolTreasury,
+      authority: authority.publicKey,
+      systemProgram: SystemProgram.programId,
+    } as any).signers([authority]).rpc();
+    await airdrop(poolTreasury, 2);
+
+    await program.methods
+      .createLiquidityPool()
+      .accounts({
+        gameConfig: poolGameConfig,
+        treasury: poolTreasury,
+        lpMint,
+        authority: authority.publicKey,
+        lpTokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      } as any)
+      .signers([authority])
+      .rpc();
+
+    const liquidityProvider = Keypair.generate();
+    await airdrop(liquidityProvider.publicKey, 3);
+    const authorityShares = await createTokenAccount(authority, lpMint);
+    const providerShares = await createTokenAccount(liquidityProvider, lpMint);
+    const depositLiquidity = (depositor: Keypair, sharesAccount: PublicKey, amount: BN) =>
+      program.methods
+        .depositLiquidity(amount)
+        .accounts({
+          gameConfig: poolGameConfig,
+          treasury: poolTreasury,
+          lpMint,
+          depositorLpAccount: sharesAccount,
+          lpTokenProgram: TOKEN_PROGRAM_ID,
+          betMint: null,
+          tokenVault: null,
+          depositorTokenAccount: null,
+          tokenProgram: null,
+          depositor: depositor.publicKey,
+          systemProgram: SystemProgram.programId,
+        } as any)
+        .signers([depositor])
+        .rpc();
+
+    // Outsiders can't seed the pool and take the operator's bankroll with it
+    try {
+      await depositLiquidity(liquidityProvider, providerShares, new BN(LAMPORTS_PER_SOL));
+      expect.fail("Should have thrown an error for seeding by a non-authority");
+    } catch (error) {
+      expect(error.message).to.include("Unauthorized");
+    }
+
+    // The operator's seed deposit is issued shares for the whole treasury, then the LP buys in at NAV
+    await depositLiquidity(authority, authorityShares, new BN(LAMPORTS_PER_SOL));
+    const rent = await provider.connection.getMinimumBalanceForRentExemption(0);
+    expect((await tokenBalance(authorityShares)).toNumber()).to.equal(3 * LAMPORTS_PER_SOL - rent);
+    await depositLiquidity(liquidityProvider, providerShares, new BN(LAMPORTS_PER_SOL));
+    expect((await tokenBalance(providerShares)).toNumber()).to.equal(LAMPORTS_PER_SOL);
+
+    // The operator can no longer pull funds outside the share accounting
+    await program.methods
+      .requestWithdrawal(new BN(LAMPORTS_PER_SOL))
+      .accounts({ gameConfig: poolGameConfig, authority: authority.publicKey } as any)
+      .signers([authority])
+      .rpc();
+    try {
+      await program.methods
+        .withdrawTreasury()
+        .accounts({
+          gameConfig: poolGameConfig,
+          treasury: poolTreasury,
+          betMint: null,
+          tokenVault: null,
+          authorityTokenAccount: null,
+          tokenProgram: null,
+          authority: authority.publicKey,
+          systemProgram: SystemProgram.programId,
+        } as any)
+        .signers([authority])
+        .rpc();
+      expect.fail("Should have thrown an error for an operator withdrawal from a pooled treasury");
+    } catch (error) {
+      expect(error.message).to.include("LiquidityPoolActive");
+    }
+
+    // House profit lands in the treasury and raises the value of every share
+    await program.methods
+      .depositTreasury(new BN(LAMPORTS_PER_SOL / 2))
+      .accounts({
+        gameConfig: poolGameConfig,
+        treasury: poolTreasury,
+        betMint: null,
+        tokenVault: null,
+        depositorTokenAccount: null,
+        tokenProgram: null,
+        depositor: authority.publicKey,
+        systemProgram: SystemProgram.programId,
+      } as any)
+      .signers([authority])
+      .rpc();
+
+    const balanceBefore = await provider.connection.getBalance(liquidityProvider.publicKey);
+    await program.methods
+      .withdrawLiquidity(new BN(LAMPORTS_PER_SOL))
+      .accounts({
+        gameConfig: poolGameConfig,
+        treasury: poolTreasury,
+        lpMint,
+        providerLpAccount: providerShares,
+        lpTokenProgram: TOKEN_PROGRAM_ID,
+        betMint: null,
+        tokenVault: null,
+        providerTokenAccount: null,
+        tokenProgram: null,
+        provider: liquidityProvider.publicKey,
+        systemProgram: SystemProgram.programId,
+      } as any)
+      .signers([liquidityProvider])
+      .rpc();
+    const received = (await provider.connection.getBalance(liquidityProvider.publicKey)) - balanceBefore;
+    expect(received).to.be.greaterThan(LAMPORTS_PER_SOL);
+    expect((await tokenBalance(providerShares)).toNumber()).to.equal(0);
+    console.log(`✅ LP redeemed 1 SOL of shares for ${received / LAMPORTS_PER_SOL} SOL`);
+  });
+
+  it("Locks LP shares while a bet is open so a player LP cannot redeem ahead of a known win", async () => {
+    const { machineConfig, machineTreasury, machinePaytable, machinePlayerState } = await createMachine(new BN(5));
+    const [lpMint] = PublicKey.findProgramAddressSync([LP_MINT_SEED, machineConfig.toBuffer()], program.programId);
+    await program.methods
+      .createLiquidityPool()
+      .accounts({
+        gameConfig: machineConfig,
+        treasury: machineTreasury,
+        lpMint,
+        authority: authority.publicKey,
+        lpTokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      } as any)
+      .signers([authority])
+      .rpc();
+    const authorityShares = await createTokenAccount(authority, lpMint);
+    const playerShares = await createTokenAccount(player, lpMint);
+    const depositLiquidity = (depositor: Keypair, sharesAccount: PublicKey, amount: BN) =>
+      program.methods
+        .depositLiquidity(amount)
+        .accounts({
+          gameConfig: machineConfig,
+          treasury: machineTreasury,
+          lpMint,
+          depositorLpAccount: sharesAccount,
+          lpTokenProgram: TOKEN_PROGRAM_ID,
+          betMint: null,
+          tokenVault: null,
+          depositorTokenAccount: null,
+          tokenProgram: null,
+          depositor: depositor.publicKey,
+          systemProgram: SystemProgram.programId,
+        } as any)
+        .signers([depositor])
+        .rpc();
+    const withdrawLiquidity = (shares: BN) =>
+      program.methods
+        .withdrawLiquidity(shares)
+        .accounts({
+          gameConfig: machineConfig,
+          treasury: machineTreasury,
+          lpMint,
+          providerLpAccount: playerShares,
+          lpTokenProgram: TOKEN_PROGRAM_ID,
+          betMint: null,
+          tokenVault: null,
+          providerTokenAccount: null,
+          tokenProgram: null,
+          provider: player.publicKey,
+          systemProgram: SystemProgram.programId,
+        } as any)
+        .signers([player])
+        .rpc();
+
+    // The player is also an LP of the machine they bet on
+    await depositLiquidity(authority, authorityShares, new BN(LAMPORTS_PER_SOL));
+    await depositLiquidity(player, playerShares, new BN(LAMPORTS_PER_SOL / 2));
+    const shares = await tokenBalance(playerShares);
+
+    const nonce = new BN(1);
+    const secretValue = crypto.randomInt(100000);
+    const salt = crypto.randomInt(100000);
+    const [commitment] = PublicKey.findProgramAddressSync(
+      [Buffer.from("commitment"), machineConfig.toBuffer(), player.publicKey.toBuffer(), nonce.toArrayLike(Buffer, 'le', 8)],
+      program.programId
+    );
+    await program.methods
+      .commit(Array.from(createCommitmentHash(secretValue, salt, player.publicKey)), new BN(10000000), nonce)
+      .accounts({
+        gameConfig: machineConfig,
+        randomnessClient,
+        paytable: machinePaytable,
+        treasury: machineTreasury,
+        commitment,
+        jackpotPool: null,
+        player: player.publicKey,
+        playerState: machinePlayerState,
+        systemProgram: SystemProgram.programId,
+      } as any)
+      .signers([player])
+      .rpc();
+
+    // Once the entropy slot has passed the player can compute the outcome, but the NAV does not
+    // reflect it until the reveal, so shares can neither be redeemed nor bought meanwhile
+    await sleep(3);
+    try {
+      await withdrawLiquidity(shares);
+      expect.fail("Should have thrown an error for a redemption with an open bet");
+    } catch (error) {
+      expect(error.message).to.include("LiquidityLocked");
+    }
+    try {
+      await depositLiquidity(player, playerShares, new BN(LAMPORTS_PER_SOL / 2));
+      expect.fail("Should have thrown an error for a deposit with an open bet");
+    } catch (error) {
+      expect(error.message).to.include("LiquidityLocked");
+    }
+
+    // After the reveal the win or loss is in the NAV and the player redeems at the settled price
+    await program.methods
+      .revealAndSpin(new BN(secretValue), new BN(salt))
+      .accounts({
+        gameConfig: machineConfig,
+        paytable: machinePaytable,
+        treasury: machineTreasury,
+        commitment,
+        jackpotPool: null,
+        ...NO_TOKEN_ACCOUNTS,
+        spinResult: null,
+        player: player.publicKey,
+        playerState: machinePlayerState,
+        systemProgram: SystemProgram.programId,
+      } as any)
+      .signers([player])
+      .rpc();
+    const rent = await provider.connection.getMinimumBalanceForRentExemption(0);
+    const nav = (await provider.connection.getBalance(machineTreasury)) - rent;
+    const supply = new BN((await provider.connection.getTokenSupply(lpMint)).value.amount);
+    const balanceBefore = await provider.connection.getBalance(player.publicKey);
+    await withdrawLiquidity(shares);
+    const received = (await provider.connection.getBalance(player.publicKey)) - balanceBefore;
+    expect(received).to.equal(shares.mul(new BN(nav)).div(supply).toNumber());
+    console.log("✅ LP shares locked until the open bet settled");
+  });
+
+  it("Emits typed events for commit, settlement, config and bankroll changes", async () => {
+    const nonce = new BN(1300);
+    const betAmount = new BN(10000000);
+    const secretValue = crypto.randomInt(100000);
+    const salt = crypto.randomInt(100000);
+    const [commitment] = PublicKey.findProgramAddressSync(
+      [Buffer.from("commitment"), gameConfig.toBuffer(), player.publicKey.toBuffer(), nonce.toArrayLike(Buffer, 'le', 8)],
+      program.programId
+    );
+    const [spinResult] = PublicKey.findProgramAddressSync(
+      [SPIN_RESULT_SEED, gameConfig.toBuffer(), player.publicKey.toBuffer(), nonce.toArrayLike(Buffer, 'le', 8)],
+      program.programId
+    );
+
+    const commitSignature = await program.methods
+      .commit(Array.from(createCommitmentHash(secretValue, salt, player.publicKey)), betAmount, nonce)
+      .accounts({
+        gameConfig,
+        randomnessClient,
+        paytable,
+        treasury,
+        commitment,
+        jackpotPool,
+        player: player.publicKey,
+        playerState,
+        systemProgram: SystemProgram.programId,
+      } as any)
+      .signers([player])
+      .rpc({ commitment: "confirmed" });
+    const betCommitted = (await eventsOf(commitSignature)).find((event) => event.name === "betCommitted");
+    expect(betCommitted).to.not.be.undefined;
+    expect(betCommitted.data.commitment.toString()).to.equal(commitment.toString());
+    expect(betCommitted.data.nonce.toNumber()).to.equal(1300);
+    expect(betCommitted.data.betAmount.toString()).to.equal(betAmount.toString());
+    expect(betCommitted.data.freeSpin).to.be.false;
+
+    await sleep(3);
+    const revealSignature = await program.methods
+      .revealAndSpin(new BN(secretValue), new BN(salt))
+      .accounts({
+        gameConfig,
+        paytable,
+        treasury,
+        commitment,
+        jackpotPool,
+        ...NO_TOKEN_ACCOUNTS,
+        spinResult,
+        player: player.publicKey,
+        playerState,
+        systemProgram: SystemProgram.programId,
+      } as any)
+      .signers([player])
+      .rpc({ commitment: "confirmed" });
+    const spinSettled = (await eventsOf(revealSignature)).find((event) => event.name === "spinSettled");
+    expect(spinSettled).to.not.be.undefined;
+    expect(spinSettled.data.commitment.toString()).to.equal(commitment.toString());
+    expect(spinSettled.data.source).to.have.property("commitReveal");
+    expect(spinSettled.data.payout.toString()).to.equal(
+      betAmount.mul(spinSettled.data.multiplier).toString()
+    );
+    expect(spinSettled.data.tier).to.be.a("string");
+
+    const configSignature = await program.methods
+      .updateConfig(new BN(10000000), new BN(1000000000), new BN(4))
+      .accounts({ gameConfig, authority: authority.publicKey } as any)
+      .signers([authority])
+      .rpc({ commitment: "confirmed" });
+    const configUpdated = (await eventsOf(configSignature)).find((event) => event.name === "configUpdated");
+    expect(configUpdated).to.not.be.undefined;
+    expect(configUpdated.data.gameConfig.toString()).to.equal(gameConfig.toString());
+    expect(configUpdated.data.minDelaySlots.toNumber()).to.equal(4);
+
+    const closeSignature = await program.methods
+      .closeSpinResult()
+      .accounts({ spinResult, player: player.publicKey } as any)
+      .signers([player])
+      .rpc({ commitment: "confirmed" });
+    const spinResultClosed = (await eventsOf(closeSignature)).find((event) => event.name === "spinResultClosed");
+    expect(spinResultClosed).to.not.be.undefined;
+    expect(spinResultClosed.data.spinResult.toString()).to.equal(spinResult.toString());
+    expect(spinResultClosed.data.nonce.toNumber()).to.equal(1300);
+
+    // Treasury deposits and the authority's withdrawals
+    const bankrollAmount = new BN(LAMPORTS_PER_SOL / 10);
+    const depositSignature = await program.methods
+      .depositTreasury(bankrollAmount)
+      .accounts({
+        gameConfig,
+        treasury,
+        betMint: null,
+        tokenVault: null,
+        depositorTokenAccount: null,
+        tokenProgram: null,
+        depositor: authority.publicKey,
+        systemProgram: SystemProgram.programId,
+      } as any)
+      .signers([authority])
+      .rpc({ commitment: "confirmed" });
+    const treasuryDeposited = (await eventsOf(depositSignature)).find((event) => event.name === "treasuryDeposited");
+    expect(treasuryDeposited).to.not.be.undefined;
+    expect(treasuryDeposited.data.depositor.toString()).to.equal(authority.publicKey.toString());
+    expect(treasuryDeposited.data.amount.toString()).to.equal(bankrollAmount.toString());
+
+    await program.methods
+      .requestWithdrawal(bankrollAmount)
+      .accounts({ gameConfig, authority: authority.publicKey } as any)
+      .signers([authority])
+      .rpc();
+    const withdrawSignature = await program.methods
+      .withdrawTreasury()
+      .accounts({
+        gameConfig,
+        treasury,
+        betMint: null,
+        tokenVault: null,
+        authorityTokenAccount: null,
+        tokenProgram: null,
+        authority: authority.publicKey,
+        systemProgram: SystemProgram.programId,
+      } as any)
+      .signers([authority])
+      .rpc({ commitment: "confirmed" });
+    const treasuryWithdrawn = (await eventsOf(withdrawSignature)).find((event) => event.name === "treasuryWithdrawn");
+    expect(treasuryWithdrawn).to.not.be.undefined;
+    expect(treasuryWithdrawn.data.authority.toString()).to.equal(authority.publicKey.toString());
+    expect(treasuryWithdrawn.data.amount.toString()).to.equal(bankrollAmount.toString());
+
+    // LP deposits and redemptions, on a machine of their own
+    const { machineConfig, machineTreasury } = await createMachine(new BN(7));
+    const [lpMint] = PublicKey.findProgramAddressSync([LP_MINT_SEED, machineConfig.toBuffer()], program.programId);
+    await program.methods
+      .createLiquidityPool()
+      .accounts({
+        gameConfig: machineConfig,
+        treasury: machineTreasury,
+        lpMint,
+        authority: authority.publicKey,
+        lpTokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      } as any)
+      .signers([authority])
+      .rpc();
+    const authorityShares = await createTokenAccount(authority, lpMint);
+    const liquiditySignature = await program.methods
+      .depositLiquidity(new BN(LAMPORTS_PER_SOL))
+      .accounts({
+        gameConfig: machineConfig,
+        treasury: machineTreasury,
+        lpMint,
+        depositorLpAccount: authorityShares,
+        lpTokenProgram: TOKEN_PROGRAM_ID,
+        betMint: null,
+        tokenVault: null,
+        depositorTokenAccount: null,
+        tokenProgram: null,
+        depositor: authority.publicKey,
+        systemProgram: SystemProgram.programId,
+      } as any)
+      .signers([authority])
+      .rpc({ commitment: "confirmed" });
+    const liquidityDeposited = (await eventsOf(liquiditySignature)).find((event) => event.name === "liquidityDeposited");
+    expect(liquidityDeposited).to.not.be.undefined;
+    expect(liquidityDeposited.data.gameConfig.toString()).to.equal(machineConfig.toString());
+    expect(liquidityDeposited.data.amount.toNumber()).to.equal(LAMPORTS_PER_SOL);
+    const shares = await tokenBalance(authorityShares);
+    expect(liquidityDeposited.data.shares.toString()).to.equal(shares.toString());
+
+    const redeemed = shares.divn(2);
+    const redeemSignature = await program.methods
+      .withdrawLiquidity(redeemed)
+      .accounts({
+        gameConfig: machineConfig,
+        treasury: machineTreasury,
+        lpMint,
+        providerLpAccount: authorityShares,
+        lpTokenProgram: TOKEN_PROGRAM_ID,
+        betMint: null,
+        tokenVault: null,
+        providerTokenAccount: null,
+        tokenProgram: null,
+        provider: authority.publicKey,
+        systemProgram: SystemProgram.programId,
+      } as any)
+      .signers([authority])
+      .rpc({ commitment: "confirmed" });
+    const liquidityWithdrawn = (await eventsOf(redeemSignature)).find((event) => event.name === "liquidityWithdrawn");
+    expect(liquidityWithdrawn).to.not.be.undefined;
+    expect(liquidityWithdrawn.data.provider.toString()).to.equal(authority.publicKey.toString());
+    expect(liquidityWithdrawn.data.shares.toString()).to.equal(redeemed.toString());
+    expect(liquidityWithdrawn.data.nav.add(liquidityWithdrawn.data.amount).toString()).to.equal(
+      liquidityDeposited.data.nav.toString()
+    );
+    console.log(`✅ Events emitted; settled tier ${spinSettled.data.tier} paid ${spinSettled.data.payout}`);
+  });
+
+  it("Records the settled spin in a SpinResult account", async () => {
+    const nonce = new BN(1400);
+    const betAmount = new BN(10000000);
+    const secretValue = crypto.randomInt(100000);
+    const salt = crypto.randomInt(100000);
+    const commitmentHash = createCommitmentHash(secretValue, salt, player.publicKey);
+    const [commitment] = PublicKey.findProgramAddressSync(
+      [Buffer.from("commitment"), gameConfig.toBuffer(), player.publicKey.toBuffer(), nonce.toArrayLike(Buffer, 'le', 8)],
+      program.programId
+    );
+    const [spinResult] = PublicKey.findProgramAddressSync(
+      [SPIN_RESULT_SEED, gameConfig.toBuffer(), player.publicKey.toBuffer(), nonce.toArrayLike(Buffer, 'le', 8)],
+      program.programId
+    );
+
+    await program.methods
+      .commit(Array.from(commitmentHash), betAmount, nonce)
+      .accounts({
+        gameConfig,
+        randomnessClient,
+        paytable,
+        treasury,
+        commitment,
+        jackpotPool,
+        player: player.publicKey,
+        playerState,
+        systemProgram: SystemProgram.programId,
+      } as any)
+      .signers([player])
+      .rpc();
+    const { commitSlot, entropySlot } = await program.account.commitment.fetch(commitment);
+    const config = await program.account.gameConfig.fetch(gameConfig);
+    expect(entropySlot.toNumber()).to.equal(commitSlot.toNumber() + config.minDelaySlots.toNumber());
+
+    await sleep(3);
+    await program.methods
+      .revealAndSpin(new BN(secretValue), new BN(salt))
+      .accounts({
+        gameConfig,
+        paytable,
+        treasury,
+        commitment,
+        jackpotPool,
+        ...NO_TOKEN_ACCOUNTS,
+        spinResult,
+        player: player.publicKey,
+        playerState,
+        systemProgram: SystemProgram.programId,
+      } as any)
+      .signers([player])
+      .rpc();
+
+    // The record outlives the closed commitment and carries everything needed to audit it
+    const result = await program.account.spinResult.fetch(spinResult);
+    expect(result.player.toString()).to.equal(player.publicKey.toString());
+    expect(result.gameConfig.toString()).to.equal(gameConfig.toString());
+    expect(result.nonce.toNumber()).to.equal(1400);
+    expect(Buffer.from(result.commitmentHash).equals(commitmentHash)).to.be.true;
+    expect(result.secretValue.toNumber()).to.equal(secretValue);
+    expect(result.salt.toNumber()).to.equal(salt);
+    expect(result.betAmount.toString()).to.equal(betAmount.toString());
+    expect(result.payout.toString()).to.equal(betAmount.mul(result.multiplier).toString());
+    expect(result.source).to.have.property("commitReveal");
+    expect(result.slot.toNumber()).to.be.greaterThan(0);
+
+    // The mixed-in hash is the SlotHashes entry of the first slot produced at or after the entropy slot
+    expect(result.hashSlot.toNumber()).to.be.at.least(entropySlot.toNumber());
+    expect(result.hashSlot.toNumber()).to.be.below(result.slot.toNumber());
+    const slotHashes = (await provider.connection.getAccountInfo(SYSVAR_SLOT_HASHES_PUBKEY)).data;
+    const entries = Number(slotHashes.readBigUInt64LE(0));
+    const recorded = [...Array(entries).keys()]
+      .map((i) => ({ slot: Number(slotHashes.readBigUInt64LE(8 + i * 40)), hash: slotHashes.subarray(16 + i * 40, 48 + i * 40) }))
+      .filter((entry) => entry.slot >= entropySlot.toNumber())
+      .pop();
+    expect(recorded.slot).to.equal(result.hashSlot.toNumber());
+    expect(Buffer.from(result.slotHash).equals(recorded.hash)).to.be.true;
+    // Tier-mode fallback spins have no oracle value and no grid
+    expect(result.oracleValue.every((byte) => byte === 0)).to.be.true;
+    expect(result.grid.length).to.equal(0);
+    expect(result.rows).to.equal(0);
+    expect(result.reels).to.equal(0);
+
+    await program.methods
+      .closeSpinResult()
+      .accounts({ spinResult, player: player.publicKey } as any)
+      .signers([player])
+      .rpc();
+    expect(await provider.connection.getAccountInfo(spinResult)).to.be.null;
+    console.log(`✅ Spin result recorded (${result.tier}, payout ${result.payout}) and closed`);
+  });
+
+  it("Tracks lifetime statistics in the player state", async () => {
+    const before = await program.account.playerState.fetch(playerState);
+    const nonce = new BN(1500);
+    const betAmount = new BN(20000000);
+    const secretValue = crypto.randomInt(100000);
+    const salt = crypto.randomInt(100000);
+    const [commitment] = PublicKey.findProgramAddressSync(
+      [Buffer.from("commitment"), gameConfig.toBuffer(), player.publicKey.toBuffer(), nonce.toArrayLike(Buffer, 'le', 8)],
+      program.programId
+    );
+
+    await program.methods
+      .commit(Array.from(createCommitmentHash(secretValue, salt, player.publicKey)), betAmount, nonce)
+      .accounts({
+        gameConfig,
+        randomnessClient,
+        paytable,
+        treasury,
+        commitment,
+        jackpotPool,
+        player: player.publicKey,
+        playerState,
+        systemProgram: SystemProgram.programId,
+      } as any)
+      .signers([player])
+      .rpc();
+    const committed = await program.account.playerState.fetch(playerState);
+    expect(committed.nextNonce.toNumber()).to.equal(1501);
+    expect(committed.totalWagered.sub(before.totalWagered).toString()).to.equal(betAmount.toString());
+    expect(committed.spins.toString()).to.equal(before.spins.toString());
+
+    await sleep(3);
+    const signature = await program.methods
+      .revealAndSpin(new BN(secretValue), new BN(salt))
+      .accounts({
+        gameConfig,
+        paytable,
+        treasury,
+        commitment,
+        jackpotPool,
+        ...NO_TOKEN_ACCOUNTS,
+        spinResult: null,
+        player: player.publicKey,
+        playerState,
+        systemProgram: SystemProgram.programId,
+      } as any)
+      .signers([player])
+      .rpc({ commitment: "confirmed" });
+    const settled = (await eventsOf(signature)).find((event) => event.name === "spinSettled");
+    const won = settled.data.payout.add(settled.data.jackpotPayout);
+
+    const after = await program.account.playerState.fetch(playerState);
+    expect(after.spins.sub(before.spins).toNumber()).to.equal(1);
+    expect(after.totalWon.sub(before.totalWon).toString()).to.equal(won.toString());
+    expect(after.biggestWin.gte(won)).to.be.true;
+    expect(after.biggestWin.gte(before.biggestWin)).to.be.true;
+    expect(after.lastPlayed.toNumber()).to.be.at.least(committed.lastPlayed.toNumber());
+    console.log(`✅ Player stats: ${after.spins} spins, wagered ${after.totalWagered}, won ${after.totalWon}`);
+  });
+
+  it("Keeps game-wide statistics for house accounting", async () => {
+    const [gameStats] = PublicKey.findProgramAddressSync([GAME_STATS_SEED, gameConfig.toBuffer()], program.programId);
+    const before = await program.account.gameStats.fetch(gameStats);
+    expect(before.gameConfig.toString()).to.equal(gameConfig.toString());
+
+    const nonce = new BN(1600);
+    const betAmount = new BN(10000000);
+    const secretValue = crypto.randomInt(100000);
+    const salt = crypto.randomInt(100000);
+    const [commitment] = PublicKey.findProgramAddressSync(
+      [Buffer.from("commitment"), gameConfig.toBuffer(), player.publicKey.toBuffer(), nonce.toArrayLike(Buffer, 'le', 8)],
+      program.programId
+    );
+
+    await program.methods
+      .commit(Array.from(createCommitmentHash(secretValue, salt, player.publicKey)), betAmount, nonce)
+      .accounts({
+        gameConfig,
+        randomnessClient,
+        gameStats,
+        paytable,
+        treasury,
+        commitment,
+        jackpotPool,
+        player: player.publicKey,
+        playerState,
+        systemProgram: SystemProgram.programId,
+      } as any)
+      .signers([player])
+      .rpc();
+    const committed = await program.account.gameStats.fetch(gameStats);
+    const config = await program.account.gameConfig.fetch(gameConfig);
+    expect(committed.totalWagered.sub(before.totalWagered).toString()).to.equal(betAmount.toString());
+    expect(committed.outstandingLiability.toString()).to.equal(config.outstandingLiability.toString());
+
+    await sleep(3);
+    const signature = await program.methods
+      .revealAndSpin(new BN(secretValue), new BN(salt))
+      .accounts({
+        gameConfig,
+        gameStats,
+        paytable,
+        treasury,
+        commitment,
+        jackpotPool,
+        ...NO_TOKEN_ACCOUNTS,
+        spinResult: null,
+        player: player.publicKey,
+        playerState,
+        systemProgram: SystemProgram.programId,
+      } as any)
+      .signers([player])
+      .rpc({ commitment: "confirmed" });
+    const settled = (await eventsOf(signature)).find((event) => event.name === "spinSettled");
+
+    const after = await program.account.gameStats.fetch(gameStats);
+    expect(after.spins.sub(before.spins).toNumber()).to.equal(1);
+    expect(after.settledWagered.sub(before.settledWagered).toString()).to.equal(betAmount.toString());
+    expect(after.totalPaid.sub(before.totalPaid).toString()).to.equal(settled.data.payout.toString());
+    expect(after.jackpotPaid.sub(before.jackpotPaid).toString()).to.equal(settled.data.jackpotPayout.toString());
+
+    // Exactly one tier hit or miss was counted for this tier-mode spin
+    const sum = (stats) => stats.tierHits.reduce((total, hits) => total.add(hits), stats.misses);
+    expect(sum(after).sub(sum(before)).toNumber()).to.equal(1);
+
+    const expectedRtp = after.totalPaid.add(after.jackpotPaid).muln(10_000).div(after.settledWagered);
+    expect(after.realizedRtpBps.toString()).to.equal(expectedRtp.toString());
+    console.log(`✅ Game stats: ${after.spins} spins, realized RTP ${after.realizedRtpBps.toNumber() / 100}%`);
+  });
+
+  it("Settles each commitment only on the path fixed at commit time", async () => {
+    const secretValue = crypto.randomInt(100000);
+    const salt = crypto.randomInt(100000);
+    const commitAt = async (nonce: BN) => {
+      const [commitment] = PublicKey.findProgramAddressSync(
+        [Buffer.from("commitment"), gameConfig.toBuffer(), player.publicKey.toBuffer(), nonce.toArrayLike(Buffer, 'le', 8)],
+        program.programId
+      );
+      await program.methods
+        .commit(Array.from(createCommitmentHash(secretValue, salt, player.publicKey)), new BN(10000000), nonce)
+        .accounts({
+          gameConfig,
+          randomnessClient,
+          paytable,
+          treasury,
+          commitment,
+          jackpotPool,
+          player: player.publicKey,
+          playerState,
+          systemProgram: SystemProgram.programId,
+        } as any)
+        .signers([player])
+        .rpc();
+      return commitment;
+    };
+    const setSwitchboard = (enabled: boolean) =>
+      program.methods
+        .setUseSwitchboard(enabled)
+        .accounts({ authority: authority.publicKey, randomnessClient } as any)
+        .signers([authority])
+        .rpc();
+    const consume = (commitment: PublicKey) =>
+      program.methods
+        .consumeRandomness(new BN(secretValue), new BN(salt))
+        .accounts({
+          jackpotPool,
+          ...NO_TOKEN_ACCOUNTS,
+          spinResult: null,
+          player: player.publicKey,
+          playerState,
+          commitment,
+          treasury,
+          gameConfig,
+          paytable,
+          randomnessAccountData: PublicKey.default,
+          systemProgram: SystemProgram.programId,
+        } as any)
+        .signers([player])
+        .rpc();
+    const reveal = (commitment: PublicKey) =>
+      program.methods
+        .revealAndSpin(new BN(secretValue), new BN(salt))
+        .accounts({
+          gameConfig,
+          paytable,
+          treasury,
+          commitment,
+          jackpotPool,
+          ...NO_TOKEN_ACCOUNTS,
+          spinResult: null,
+          player: player.publicKey,
+          playerState,
+          systemProgram: SystemProgram.programId,
+        } as any)
+        .signers([player])
+        .rpc();
+
+    // One bet placed in fallback mode, one after the operator switches to oracle-only mode
+    const fallbackCommitment = await commitAt(new BN(1700));
+    await setSwitchboard(true);
+    const oracleCommitment = await commitAt(new BN(1701));
+    expect((await program.account.commitment.fetch(fallbackCommitment)).useSwitchboard).to.be.false;
+    expect((await program.account.commitment.fetch(oracleCommitment)).useSwitchboard).to.be.true;
+
+    try {
+      try {
+        await consume(fallbackCommitment);
+        expect.fail("Should have thrown an error for oracle settlement of a fallback commitment");
+      } catch (error) {
+        expect(error.message).to.include("SwitchboardDisabled");
+      }
+
+      // The unbound commitment still carries the default randomness account, which passes the
+      // account constraint; the status check is what stops it
+      try {
+        await consume(oracleCommitment);
+        expect.fail("Should have thrown an error for a commitment without requested randomness");
+      } catch (error) {
+        expect(error.message).to.include("RandomnessNotRequested");
+      }
+      const commitmentAccount = await program.account.commitment.fetch(oracleCommitment);
+      expect(commitmentAccount.status).to.have.property("committed");
+
+      // Switching modes does not strand the fallback bet, which settles and is gone for good
+      await sleep(3);
+      await reveal(fallbackCommitment);
+      expect(await provider.connection.getAccountInfo(fallbackCommitment)).to.be.null;
+    } finally {
+      await setSwitchboard(false);
+    }
+
+    // Nor does switching back open the fallback path to the oracle bet
+    try {
+      await reveal(oracleCommitment);
+      expect.fail("Should have thrown an error for fallback reveal of an oracle commitment");
+    } catch (error) {
+      expect(error.message).to.include("SwitchboardRequired");
+    }
+    console.log("✅ Commitments settle on the path they were placed under");
+  });
 });
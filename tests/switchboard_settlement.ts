@@ -0,0 +1,263 @@
+import { BN, Program } from "@coral-xyz/anchor";
+import { BankrunProvider } from "anchor-bankrun";
+import { ProgramTestContext, startAnchor } from "solana-bankrun";
+import { Keypair, LAMPORTS_PER_SOL, PublicKey, SystemProgram } from "@solana/web3.js";
+import { expect } from "chai";
+import { createHash } from "crypto";
+import crypto from "crypto";
+import { SlotMachine } from "../target/types/slot_machine";
+import IDL from "../target/idl/slot_machine.json";
+
+// The oracle path needs a randomness account seeded in the slot before request_randomness and
+// revealed in the very slot of consume_randomness. A live test validator can't line that up, so
+// this suite runs the program in bankrun, where the test controls the slot and writes the
+// Switchboard account itself.
+const GAME_CONFIG_SEED = Buffer.from("game_config");
+const TREASURY_SEED = Buffer.from("treasury");
+const PAYTABLE_SEED = Buffer.from("paytable");
+const GAME_STATS_SEED = Buffer.from("game_stats");
+const PLAYER_STATE_SEED = Buffer.from("player_state");
+const SPIN_RESULT_SEED = Buffer.from("spin_result");
+const MACHINE_ID = new BN(0);
+const NO_TOKEN_ACCOUNTS = { betMint: null, tokenVault: null, playerTokenAccount: null, tokenProgram: null };
+
+const ON_DEMAND_MAINNET_PID = new PublicKey("SBondMDrcV3K4kxZR1HNVT7osZxAHVHgYXL5Ze1oMUv");
+const RANDOMNESS_DISCRIMINATOR = Buffer.from([10, 66, 229, 135, 220, 239, 217, 114]);
+
+const ODDS_DENOMINATOR = new BN(1_000_000);
+const PAYOUT_TIERS = [
+  { threshold: new BN(900_000), multiplier: new BN(5), label: "Big Win!", jackpot: false },  // 10%
+  { threshold: new BN(700_000), multiplier: new BN(1), label: "Break Even", jackpot: false }, // 20%
+];
+const MAX_RTP_BPS = 9600;
+
+// Switchboard on-demand RandomnessAccountData: discriminator, then authority, queue,
+// seed_slothash, seed_slot, oracle, reveal_slot, value and 224 reserved bytes
+function randomnessAccountData(seedSlot: bigint, revealSlot: bigint, value: Buffer): Buffer {
+  const data = Buffer.alloc(8 + 400);
+  RANDOMNESS_DISCRIMINATOR.copy(data, 0);
+  data.writeBigUInt64LE(seedSlot, 8 + 96);
+  data.writeBigUInt64LE(revealSlot, 8 + 136);
+  value.copy(data, 8 + 144);
+  return data;
+}
+
+function createCommitmentHash(secretValue: number, salt: number, playerPubkey: PublicKey): Buffer {
+  return createHash("sha256")
+    .update(new BN(secretValue).toArrayLike(Buffer, "le", 8))
+    .update(new BN(salt).toArrayLike(Buffer, "le", 8))
+    .update(playerPubkey.toBuffer())
+    .digest();
+}
+
+// Mirrors draw_roll: the first 64-bit word below the largest multiple of the denominator
+function drawRoll(hash: Buffer, denominator: BN): BN {
+  const max = new BN(1).shln(64).subn(1);
+  const zone = max.sub(max.mod(denominator).addn(1).mod(denominator));
+  for (;;) {
+    for (let offset = 0; offset < 32; offset += 8) {
+      const value = new BN(hash.subarray(offset, offset + 8), "le");
+      if (value.lte(zone)) {
+        return value.mod(denominator).addn(1);
+      }
+    }
+    hash = createHash("sha256").update(hash).digest();
+  }
+}
+
+// Mirrors consume_randomness: the revealed value mixed with the player's committed entropy
+function oracleRoll(value: Buffer, secretValue: number, salt: number, timestamp: BN, player: PublicKey, betAmount: BN): BN {
+  const hash = createHash("sha256")
+    .update(value)
+    .update(new BN(secretValue).toArrayLike(Buffer, "le", 8))
+    .update(new BN(salt).toArrayLike(Buffer, "le", 8))
+    .update(timestamp.toTwos(64).toArrayLike(Buffer, "le", 8))
+    .update(player.toBuffer())
+    .update(betAmount.toArrayLike(Buffer, "le", 8))
+    .digest();
+  return drawRoll(hash, ODDS_DENOMINATOR);
+}
+
+describe("slot_machine Switchboard settlement (bankrun)", () => {
+  let context: ProgramTestContext;
+  let program: Program<SlotMachine>;
+  let authority: Keypair;
+  const player = Keypair.generate();
+  let gameConfig: PublicKey;
+  let treasury: PublicKey;
+  let randomnessClient: PublicKey;
+  let gameStats: PublicKey;
+  let paytable: PublicKey;
+  let playerState: PublicKey;
+
+  const fund = (address: PublicKey, lamports: number) =>
+    context.setAccount(address, { lamports, data: Buffer.alloc(0), owner: SystemProgram.programId, executable: false });
+  const writeRandomness = (address: PublicKey, data: Buffer) =>
+    context.setAccount(address, { lamports: LAMPORTS_PER_SOL, data, owner: ON_DEMAND_MAINNET_PID, executable: false });
+  const currentSlot = async () => (await context.banksClient.getClock()).slot;
+  const balance = async (address: PublicKey) => Number(await context.banksClient.getBalance(address));
+
+  before(async () => {
+    context = await startAnchor(".", [], []);
+    const provider = new BankrunProvider(context);
+    program = new Program<SlotMachine>(IDL as SlotMachine, provider);
+    authority = context.payer;
+    fund(player.publicKey, 5 * LAMPORTS_PER_SOL);
+
+    gameConfig = PublicKey.findProgramAddressSync(
+      [GAME_CONFIG_SEED, authority.publicKey.toBuffer(), MACHINE_ID.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    )[0];
+    [treasury] = PublicKey.findProgramAddressSync([TREASURY_SEED, gameConfig.toBuffer()], program.programId);
+    [randomnessClient] = PublicKey.findProgramAddressSync(
+      [Buffer.from("randomness"), authority.publicKey.toBuffer()],
+      program.programId
+    );
+    [gameStats] = PublicKey.findProgramAddressSync([GAME_STATS_SEED, gameConfig.toBuffer()], program.programId);
+    [paytable] = PublicKey.findProgramAddressSync([PAYTABLE_SEED, gameConfig.toBuffer()], program.programId);
+    [playerState] = PublicKey.findProgramAddressSync(
+      [PLAYER_STATE_SEED, gameConfig.toBuffer(), player.publicKey.toBuffer()],
+      program.programId
+    );
+
+    await program.methods
+      .initialize(MACHINE_ID)
+      .accounts({ gameConfig, treasury, gameStats, authority: authority.publicKey, systemProgram: SystemProgram.programId } as any)
+      .rpc();
+    await program.methods
+      .initRandomnessClient()
+      .accounts({ authority: authority.publicKey, randomnessClient, systemProgram: SystemProgram.programId } as any)
+      .rpc();
+    await program.methods
+      .createPaytable(PAYOUT_TIERS, ODDS_DENOMINATOR, MAX_RTP_BPS)
+      .accounts({ gameConfig, paytable, authority: authority.publicKey, systemProgram: SystemProgram.programId } as any)
+      .rpc();
+    await program.methods
+      .setUseSwitchboard(true)
+      .accounts({ authority: authority.publicKey, randomnessClient } as any)
+      .rpc();
+    await program.methods
+      .depositTreasury(new BN(2 * LAMPORTS_PER_SOL))
+      .accounts({
+        gameConfig,
+        treasury,
+        betMint: null,
+        tokenVault: null,
+        depositorTokenAccount: null,
+        tokenProgram: null,
+        depositor: authority.publicKey,
+        systemProgram: SystemProgram.programId,
+      } as any)
+      .rpc();
+  });
+
+  it("Settles an oracle commitment with the revealed Switchboard value", async () => {
+    const nonce = new BN(1);
+    const secretValue = crypto.randomInt(100000);
+    const salt = crypto.randomInt(100000);
+    const betAmount = new BN(100_000_000); // 0.1 SOL
+    const [commitment] = PublicKey.findProgramAddressSync(
+      [Buffer.from("commitment"), gameConfig.toBuffer(), player.publicKey.toBuffer(), nonce.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    const [spinResult] = PublicKey.findProgramAddressSync(
+      [SPIN_RESULT_SEED, gameConfig.toBuffer(), player.publicKey.toBuffer(), nonce.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+
+    await program.methods
+      .commit(Array.from(createCommitmentHash(secretValue, salt, player.publicKey)), betAmount, nonce)
+      .accounts({
+        gameConfig,
+        randomnessClient,
+        gameStats,
+        paytable,
+        treasury,
+        commitment,
+        jackpotPool: null,
+        player: player.publicKey,
+        playerState,
+        systemProgram: SystemProgram.programId,
+      } as any)
+      .signers([player])
+      .rpc();
+    const placed = await program.account.commitment.fetch(commitment);
+    expect(placed.useSwitchboard).to.be.true;
+
+    // The oracle commits to its randomness in the slot before the request
+    const randomnessAccount = Keypair.generate().publicKey;
+    const requestSlot = (await currentSlot()) + BigInt(5);
+    context.warpToSlot(requestSlot);
+    writeRandomness(randomnessAccount, randomnessAccountData(requestSlot - BigInt(1), BigInt(0), Buffer.alloc(32)));
+    await program.methods
+      .requestRandomness()
+      .accounts({
+        player: player.publicKey,
+        commitment,
+        gameConfig,
+        randomnessAccountData: randomnessAccount,
+        systemProgram: SystemProgram.programId,
+      } as any)
+      .signers([player])
+      .rpc();
+    const requested = await program.account.commitment.fetch(commitment);
+    expect(requested.status).to.have.property("randomnessRequested");
+    expect(requested.randomnessSeedSlot.toString()).to.equal((requestSlot - BigInt(1)).toString());
+
+    // Pick an oracle value that lands on a paying tier, so the payout path is exercised too
+    let value: Buffer;
+    let roll: BN;
+    do {
+      value = crypto.randomBytes(32);
+      roll = oracleRoll(value, secretValue, salt, placed.timestamp, player.publicKey, betAmount);
+    } while (roll.lte(PAYOUT_TIERS[PAYOUT_TIERS.length - 1].threshold));
+    const tier = PAYOUT_TIERS.find((candidate) => roll.gt(candidate.threshold));
+
+    // The oracle reveals in a later slot, and the value is only readable within that slot
+    const revealSlot = requestSlot + BigInt(2);
+    context.warpToSlot(revealSlot);
+    writeRandomness(randomnessAccount, randomnessAccountData(requestSlot - BigInt(1), revealSlot, value));
+
+    const rent = await balance(commitment);
+    const treasuryBefore = await balance(treasury);
+    const playerBefore = await balance(player.publicKey);
+    await program.methods
+      .consumeRandomness(new BN(secretValue), new BN(salt))
+      .accounts({
+        jackpotPool: null,
+        ...NO_TOKEN_ACCOUNTS,
+        spinResult,
+        player: player.publicKey,
+        playerState,
+        commitment,
+        treasury,
+        gameConfig,
+        gameStats,
+        paytable,
+        randomnessAccountData: randomnessAccount,
+        systemProgram: SystemProgram.programId,
+      } as any)
+      .signers([player])
+      .rpc();
+
+    // The outcome is the one derived from the revealed value, and the treasury paid it
+    const payout = betAmount.mul(tier.multiplier).toNumber();
+    const result = await program.account.spinResult.fetch(spinResult);
+    expect(result.source).to.have.property("switchboard");
+    expect(result.randomValue.toString()).to.equal(roll.toString());
+    expect(result.tier).to.equal(tier.label);
+    expect(result.multiplier.toString()).to.equal(tier.multiplier.toString());
+    expect(result.payout.toNumber()).to.equal(payout);
+    expect(result.randomnessAccount.toBase58()).to.equal(randomnessAccount.toBase58());
+    expect(Buffer.from(result.oracleValue).equals(value)).to.be.true;
+
+    expect(await context.banksClient.getAccount(commitment)).to.be.null;
+    expect(treasuryBefore - (await balance(treasury))).to.equal(payout);
+    const spinResultRent = await balance(spinResult);
+    expect((await balance(player.publicKey)) - playerBefore).to.equal(payout + rent - spinResultRent);
+
+    const config = await program.account.gameConfig.fetch(gameConfig);
+    expect(config.outstandingLiability.toNumber()).to.equal(0);
+    console.log(`✅ Oracle spin settled with the revealed value (${tier.label}, payout ${payout})`);
+  });
+});